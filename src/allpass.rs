@@ -0,0 +1,205 @@
+use crate::channel::{self, SpecfilterSos};
+
+/// Frequencies at which a decomposition is checked against the filter, spread evenly between DC and Nyquist.
+const CHECK_POINTS: usize = 64;
+/// Largest deviation between the filter and its decomposition that is still accepted as exact.
+const CHECK_TOLERANCE: f64 = 1e-5;
+
+/// One branch `A1` of a doubly complementary decomposition of the designed filter, `H = g(A1 ± A2)/2`.
+/// In the passband of `H` this matches both its magnitude and its phase, and `A1 - H` is its power complementary filter.
+/// Such a decomposition only exists for some designs, mostly odd-order low-pass and high-pass ones. Otherwise the cascade is left empty and passes the input through unchanged.
+pub struct PhaseAllpass
+{
+    sections: Vec<[f64; 2]>,
+    gain: f64,
+    w: Vec<[f64; 2]>
+}
+
+impl PhaseAllpass
+{
+    pub fn new() -> Self
+    {
+        Self {
+            sections: vec![],
+            gain: 1.0,
+            w: vec![]
+        }
+    }
+
+    pub fn reset(&mut self)
+    {
+        self.w.fill([0.0; 2])
+    }
+
+    /// Whether the current filter has a decomposition, so that the allpass is phase-matched to it.
+    pub fn is_matched(&self) -> bool
+    {
+        !self.sections.is_empty()
+    }
+
+    /// Rebuilds the allpass for a newly designed filter.
+    /// The poles are sorted by angle and split into alternating branches, which is the decomposition of the classical odd-order designs.
+    /// The split is only kept if it reproduces the filter, with either branch and either sign.
+    /// The state is kept if the structure is unchanged, so the dry path does not click.
+    pub fn update(&mut self, sos: &SpecfilterSos)
+    {
+        let mut poles = channel::poles(sos);
+        poles.sort_by(|a, b| a.1.atan2(a.0).total_cmp(&b.1.atan2(b.0)));
+
+        // Each section is stored as the denominator [a1, a2], with the numerator being its mirror image
+        let section = |&(re, im): &(f64, f64)| if im == 0.0
+        {
+            [-re, 0.0]
+        }
+        else
+        {
+            [-2.0*re, re*re + im*im]
+        };
+        let branches: [Vec<[f64; 2]>; 2] = [0, 1].map(|first| poles.iter()
+            .skip(first)
+            .step_by(2)
+            .map(section)
+            .collect()
+        );
+
+        let matched = [(0, 1), (1, 0)].into_iter()
+            .flat_map(|(i, j)| [(i, j, 1.0), (i, j, -1.0)])
+            .flat_map(|(i, j, sign)| [(i, j, sign, 1.0), (i, j, sign, -1.0)])
+            .find(|&(i, j, sign, gain)| (0..CHECK_POINTS).all(|k| {
+                let omega = core::f64::consts::PI*(k as f64 + 0.5)/CHECK_POINTS as f64;
+                let (h_re, h_im) = channel::response(sos, omega);
+                let (a1_re, a1_im) = Self::branch_response(&branches[i], omega);
+                let (a2_re, a2_im) = Self::branch_response(&branches[j], omega);
+                let (d_re, d_im) = (gain*(a1_re + sign*a2_re)*0.5 - h_re, gain*(a1_im + sign*a2_im)*0.5 - h_im);
+                d_re.hypot(d_im) < CHECK_TOLERANCE
+            }));
+
+        let (sections, gain) = match matched
+        {
+            Some((i, _, _, gain)) if !poles.is_empty() => (branches[i].clone(), gain),
+            _ => (vec![], 1.0)
+        };
+        if sections.len() != self.w.len()
+        {
+            self.w = vec![[0.0; 2]; sections.len()]
+        }
+        self.sections = sections;
+        self.gain = gain;
+    }
+
+    /// Frequency response of a cascade of allpass sections at the given angular frequency, in radians per sample, as `(re, im)`.
+    fn branch_response(sections: &[[f64; 2]], omega: f64) -> (f64, f64)
+    {
+        sections.iter()
+            .fold((1.0, 0.0), |(re, im), &[a1, a2]| {
+                // A(e^jw) = e^(-jnw) conj(D(e^jw))/D(e^jw), so the section only contributes a phase
+                let n = if a2 == 0.0 {1.0} else {2.0};
+                let (d_re, d_im) = (1.0 + a1*omega.cos() + a2*(2.0*omega).cos(), -a1*omega.sin() - a2*(2.0*omega).sin());
+                let phase = -n*omega - 2.0*d_im.atan2(d_re);
+                let (h_re, h_im) = (phase.cos(), phase.sin());
+                (re*h_re - im*h_im, re*h_im + im*h_re)
+            })
+    }
+
+    /// Frequency response of the allpass at the given angular frequency, in radians per sample, as `(re, im)`.
+    pub fn response(&self, omega: f64) -> (f64, f64)
+    {
+        let (re, im) = Self::branch_response(&self.sections, omega);
+        (re*self.gain, im*self.gain)
+    }
+
+    /// Runs the input through the allpass and takes the filtered sample `z` from it, returning the dry path and the rejected content.
+    /// Where the filter is matched, the rejected content is its power complementary filter and is silent in the passband.
+    /// Otherwise the dry path is the input itself, so pass and reject still sum back to it.
+    pub fn complement(&mut self, x: f64, z: f64) -> (f64, f64)
+    {
+        let dry = self.next(x);
+        (dry, dry - z)
+    }
+
+    pub fn next(&mut self, mut x: f64) -> f64
+    {
+        for (&[a1, a2], w) in self.sections.iter()
+            .zip(self.w.iter_mut())
+        {
+            let b = if a2 == 0.0 {[a1, 1.0, 0.0]} else {[a2, a1, 1.0]};
+
+            // Transposed direct form II
+            let y = b[0]*x + w[0];
+            w[0] = b[1]*x - a1*y + w[1];
+            w[1] = b[2]*x - a2*y;
+            x = y;
+        }
+        if !x.is_finite()
+        {
+            self.reset();
+            return 0.0
+        }
+        x*self.gain
+    }
+}
+
+#[test]
+fn test_mix()
+{
+    use signal_processing::gen::filter::{Butter, FilterGenPlane, FilterGenType};
+    use signal_processing::systems::Zpk;
+    use signal_processing::transforms::filter::Stabilize;
+    use signal_processing::transforms::system::ToSos;
+    use signal_processing::Plane;
+
+    let sos: SpecfilterSos = Zpk::butter(5, [0.25], FilterGenType::LowPass, FilterGenPlane::Z { sampling_frequency: None })
+        .unwrap()
+        .stabilize(Plane::Z)
+        .to_sos((), ());
+    let mut allpass = PhaseAllpass::new();
+    allpass.update(&sos);
+    assert!(allpass.is_matched(), "No decomposition found for an odd-order Butterworth");
+
+    // An even mix of the filter and the matched dry path, across the passband
+    for k in 0..32
+    {
+        let omega = 0.2*core::f64::consts::PI*k as f64/31.0;
+        let (h_re, h_im) = channel::response(&sos, omega);
+        let (d_re, d_im) = allpass.response(omega);
+        let gain = (0.5*(h_re + d_re)).hypot(0.5*(h_im + d_im));
+        assert!(gain > 0.9, "Notch of {} at {} rad/sample", gain, omega);
+    }
+}
+
+#[test]
+fn test_complement()
+{
+    use signal_processing::gen::filter::{Butter, FilterGenPlane, FilterGenType};
+    use signal_processing::operations::filtering::FilterMut;
+    use signal_processing::systems::{Rtf, Zpk};
+    use signal_processing::transforms::filter::Stabilize;
+    use signal_processing::transforms::system::ToSos;
+    use signal_processing::Plane;
+
+    let design = || -> SpecfilterSos {
+        Zpk::butter(5, [0.25], FilterGenType::LowPass, FilterGenPlane::Z { sampling_frequency: None })
+            .unwrap()
+            .stabilize(Plane::Z)
+            .to_sos((), ())
+    };
+    let mut allpass = PhaseAllpass::new();
+    allpass.update(&design());
+    let mut filter = Rtf::new(design(), ());
+
+    // A tone well inside the passband
+    let x: Vec<f64> = (0..4096).map(|n| (0.05*core::f64::consts::PI*n as f64).sin())
+        .collect();
+    let z = filter.filter_mut(x.as_slice());
+    for (n, (&x, z)) in x.iter()
+        .zip(z)
+        .enumerate()
+    {
+        let (dry, reject) = allpass.complement(x, z);
+        assert!((z + reject - dry).abs() < 1e-12, "Split outputs do not sum back to the dry path");
+        if n >= 1024
+        {
+            assert!(reject.abs() < 1e-3, "Delta is not silent in the passband: {} at sample {}", reject, n);
+        }
+    }
+}
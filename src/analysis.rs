@@ -0,0 +1,113 @@
+use std::ops::Range;
+
+use vst::util::AtomicFloat;
+
+use crate::channel::SpecfilterChannel;
+
+/// Averaging time of the mono compatibility meter, in seconds.
+const METER_TIME: f64 = 0.3;
+
+/// How well the output holds up when summed to mono.
+/// It is kept with the parameters, so that anything holding the parameter object can display it.
+pub struct MonoMeter
+{
+    correlation: AtomicFloat,
+    mono_level: AtomicFloat
+}
+
+impl MonoMeter
+{
+    pub fn new() -> Self
+    {
+        Self {
+            correlation: AtomicFloat::new(1.0),
+            mono_level: AtomicFloat::new(0.0)
+        }
+    }
+
+    /// Correlation between the left and right outputs, from 1 for identical channels to -1 for channels in antiphase.
+    pub fn correlation(&self) -> f32
+    {
+        self.correlation.get()
+    }
+
+    /// Level of the mono sum relative to the average level of the two channels, in dB.
+    /// This is 0 dB for identical channels, -3 dB for uncorrelated channels, and falls towards silence as they cancel.
+    pub fn mono_level(&self) -> f32
+    {
+        self.mono_level.get()
+    }
+}
+
+/// Measures the mono compatibility of the first two outputs, which are left and right in every layout with more than one channel.
+pub struct MonoAnalyzer
+{
+    /// Running averages of l², r² and lr.
+    energy: [f64; 3]
+}
+
+impl MonoAnalyzer
+{
+    pub fn new() -> Self
+    {
+        Self {
+            energy: [0.0; 3]
+        }
+    }
+
+    pub fn reset(&mut self, meter: &MonoMeter)
+    {
+        self.energy = [0.0; 3];
+        meter.correlation.set(1.0);
+        meter.mono_level.set(0.0);
+    }
+
+    pub fn process(&mut self, meter: &MonoMeter, y: &[Vec<f64>], block: Range<usize>, rate: f64)
+    {
+        let [l, r, ..] = y
+        else
+        {
+            return
+        };
+        let coeff = (-1.0/(METER_TIME*rate)).exp();
+        for i in block
+        {
+            let now = [l[i]*l[i], r[i]*r[i], l[i]*r[i]];
+            for (energy, now) in self.energy.iter_mut()
+                .zip(now)
+            {
+                *energy = now + (*energy - now)*coeff
+            }
+        }
+        if !self.energy.iter().all(|e| e.is_finite())
+        {
+            self.energy = [0.0; 3]
+        }
+
+        let [ll, rr, lr] = self.energy;
+        let stereo = (ll + rr)*0.5;
+        if stereo > f64::MIN_POSITIVE
+        {
+            let mono = (ll + rr + 2.0*lr)*0.25;
+            meter.correlation.set((lr/(ll*rr).sqrt().max(f64::MIN_POSITIVE)).min(1.0).max(-1.0) as f32);
+            meter.mono_level.set((10.0*(mono/stereo).log10()).max(-120.0) as f32);
+        }
+    }
+}
+
+/// How much of the left and right filter responses survives summing to mono at the given angular frequency, in radians per sample.
+/// This is |H_L + H_R|/(|H_L| + |H_R|), which is 1 where the filters are in phase and falls as they cancel.
+pub fn mono_response(left: &SpecfilterChannel, right: &SpecfilterChannel, omega: f64) -> f64
+{
+    let (l_re, l_im) = left.response(omega);
+    let (r_re, r_im) = right.response(omega);
+    let sum = l_re.hypot(l_im) + r_re.hypot(r_im);
+    if sum > 0.0
+    {
+        (l_re + r_re).hypot(l_im + r_im)/sum
+    }
+    else
+    {
+        1.0
+    }
+}
\ No newline at end of file
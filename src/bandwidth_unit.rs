@@ -0,0 +1,39 @@
+use core::fmt::Display;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BandwidthUnit
+{
+    Hertz,
+    Octaves,
+    Percent,
+    Q,
+    Erb
+}
+
+impl BandwidthUnit
+{
+    pub const VARIANT_COUNT: usize = core::mem::variant_count::<Self>();
+    pub const VARIANTS: [Self; Self::VARIANT_COUNT] = [
+        Self::Hertz,
+        Self::Octaves,
+        Self::Percent,
+        Self::Q,
+        Self::Erb
+    ];
+    pub const VARIANT_NAMES: [&'static str; Self::VARIANT_COUNT] = [
+        "Hz",
+        "Octaves",
+        "Percent",
+        "Q",
+        "ERB"
+    ];
+}
+
+impl Display for BandwidthUnit
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", Self::VARIANT_NAMES[*self as usize])
+    }
+}
\ No newline at end of file
@@ -0,0 +1,385 @@
+use array_math::{ArrayOps, SliceMath};
+use num_traits::float::TotalOrder;
+use num_traits::Zero;
+use signal_processing::analysis::FiltOrd;
+use signal_processing::gen::filter::{Butter, Cheby1, Cheby2, Ellip, FilterGenError, FilterGenPlane};
+use signal_processing::systems::{Rtf, Sos, Tf, Zpk};
+use signal_processing::transforms::filter::Stabilize;
+use signal_processing::transforms::system::ToSos;
+use signal_processing::Plane;
+
+use crate::allpass::PhaseAllpass;
+use crate::filter_kind::FilterKind;
+use crate::filter_type::FilterType;
+use crate::makeup_mode::MakeupMode;
+use crate::parameters::SpecfilterParamData;
+use crate::smoothed::Smoothed;
+use crate::tube_stage::TubeStage;
+
+const MAX_ORDER: usize = 64;
+const MAKEUP_POINTS: usize = 32;
+/// The tail is counted until the ringing has decayed by this many decibels.
+const TAIL_DB: f64 = 90.0;
+
+pub type SpecfilterSos = Sos<f64, [f64; 3], [f64; 3], Vec<Tf<f64, [f64; 3], [f64; 3]>>>;
+
+/// Splits the denominator sections of the filter into poles, given as `(re, im)` with `im >= 0`.
+pub fn poles(sos: &SpecfilterSos) -> Vec<(f64, f64)>
+{
+    let mut poles = vec![];
+    for section in sos.sos.iter()
+    {
+        let [a0, a1, a2] = *section.a;
+        if a0 == 0.0
+        {
+            // First-order sections are stored with a leading zero, as `a1 z + a2`
+            if a1 != 0.0
+            {
+                poles.push((-a2/a1, 0.0))
+            }
+            continue
+        }
+        let (a1, a2) = (a1/a0, a2/a0);
+        if a2 == 0.0
+        {
+            if a1 != 0.0
+            {
+                poles.push((-a1, 0.0))
+            }
+            continue
+        }
+        let disc = a1*a1 - 4.0*a2;
+        if disc >= 0.0
+        {
+            poles.push(((-a1 + disc.sqrt())*0.5, 0.0));
+            poles.push(((-a1 - disc.sqrt())*0.5, 0.0));
+        }
+        else
+        {
+            poles.push((-a1*0.5, (-disc).sqrt()*0.5));
+        }
+    }
+    poles
+}
+
+/// Frequency response of the filter at the given angular frequency, in radians per sample, as `(re, im)`.
+pub fn response(sos: &SpecfilterSos, omega: f64) -> (f64, f64)
+{
+    // Evaluated at z^-1 = e^(-j omega), for each section in turn
+    let eval = |c: &[f64; 3]| (
+        c[0] + c[1]*omega.cos() + c[2]*(2.0*omega).cos(),
+        -c[1]*omega.sin() - c[2]*(2.0*omega).sin()
+    );
+    sos.sos.iter()
+        .fold((1.0, 0.0), |(re, im), sos| {
+            let (b_re, b_im) = eval(&sos.b);
+            let (a_re, a_im) = eval(&sos.a);
+            let a_norm = a_re*a_re + a_im*a_im;
+            let (h_re, h_im) = ((b_re*a_re + b_im*a_im)/a_norm, (b_im*a_re - b_re*a_im)/a_norm);
+            (re*h_re - im*h_im, re*h_im + im*h_re)
+        })
+}
+
+/// The filter of a single audio channel.
+/// Each channel is designed from its own, possibly modulated, spec.
+pub struct SpecfilterChannel
+{
+    pub filter: Rtf<f64, SpecfilterSos>,
+    pub filter_type: FilterType,
+    pub tube: TubeStage,
+    pub allpass: PhaseAllpass,
+    /// The makeup gain as applied, gliding towards the one of the current filter.
+    pub makeup_gain: Smoothed,
+    /// How long the filter keeps ringing after the input stops, in samples.
+    filter_tail: f64,
+    param: Option<SpecfilterParamData>,
+    makeup: Option<(MakeupMode, f64)>
+}
+
+impl SpecfilterChannel
+{
+    pub fn new() -> Self
+    {
+        Self {
+            filter: Rtf::new(Sos::one(), ()),
+            filter_type: FilterType::AllPass,
+            tube: TubeStage::new(),
+            allpass: PhaseAllpass::new(),
+            makeup_gain: Smoothed::new(),
+            filter_tail: 0.0,
+            param: None,
+            makeup: None
+        }
+    }
+
+    pub fn reset(&mut self)
+    {
+        self.filter.w.clear();
+        self.tube.reset();
+        self.allpass.reset();
+        self.makeup_gain.reset();
+        self.param = None;
+        self.makeup = None;
+    }
+
+    /// How long the filter and tube stage keep ringing after the input stops, in samples.
+    pub fn tail(&self, rate: f64) -> usize
+    {
+        (self.filter_tail + self.tube.tail(TAIL_DB, rate)).ceil() as usize
+    }
+
+    /// Decay time of the filter, given by its slowest pole.
+    fn filter_tail(sos: &SpecfilterSos) -> f64
+    {
+        let decay = TAIL_DB/20.0*core::f64::consts::LN_10;
+        let radius = poles(sos).into_iter()
+            .map(|(re, im)| re.hypot(im))
+            .fold(0.0, f64::max);
+        if radius > 0.0 && radius < 1.0
+        {
+            decay/-radius.ln()
+        }
+        else
+        {
+            0.0
+        }
+    }
+
+    /// Frequency response of the current filter at the given angular frequency, in radians per sample, as `(re, im)`.
+    pub fn response(&self, omega: f64) -> (f64, f64)
+    {
+        response(&self.filter.sys, omega)
+    }
+
+    /// Magnitude response of the current filter at the given angular frequency, in radians per sample.
+    pub fn magnitude(&self, omega: f64) -> f64
+    {
+        let (re, im) = self.response(omega);
+        re.hypot(im)
+    }
+
+    /// The passband gain of the current filter, measured the way the makeup mode asks for.
+    fn reference_gain(&self, mode: MakeupMode, rate: f64) -> Option<f64>
+    {
+        let data = self.param?;
+        let (freq, stop, nolb, noub) = data.frequency_data(rate as f32);
+        let omega = |f: f32| core::f64::consts::TAU*f as f64/rate;
+
+        let mut passbands = vec![];
+        if stop
+        {
+            if !nolb
+            {
+                passbands.push((1.0, freq[0]))
+            }
+            if !noub
+            {
+                passbands.push((freq[3], (rate/2.0) as f32))
+            }
+        }
+        else
+        {
+            passbands.push((freq[1], freq[2]))
+        }
+        let points: Vec<f64> = passbands.into_iter()
+            .flat_map(|(f0, f1)| (0..MAKEUP_POINTS).map(move |i| {
+                let t = i as f32/(MAKEUP_POINTS - 1) as f32;
+                (f0.log2()*(1.0 - t) + f1.log2()*t).exp2()
+            }))
+            .map(|f| self.magnitude(omega(f)))
+            .collect();
+        if points.is_empty()
+        {
+            return None
+        }
+
+        match mode
+        {
+            MakeupMode::Off => None,
+            MakeupMode::Peak => Some(points.into_iter()
+                .fold(0.0, f64::max)
+            ),
+            MakeupMode::PassbandAverage => Some((points.iter()
+                .map(|g| g.ln())
+                .sum::<f64>()/points.len() as f64
+            ).exp()),
+            MakeupMode::Center => Some(match self.filter_type
+            {
+                FilterType::HighPass => self.magnitude(core::f64::consts::PI),
+                FilterType::BandPass => self.magnitude(omega((freq[1]*freq[2]).sqrt())),
+                _ => self.magnitude(0.0)
+            })
+        }
+    }
+
+    /// The gain that brings the passband of the current filter to unity, cached until the filter is redesigned.
+    pub fn makeup(&mut self, mode: MakeupMode, rate: f64) -> f64
+    {
+        match self.makeup
+        {
+            Some((cached_mode, gain)) if cached_mode == mode => gain,
+            _ => {
+                let gain = self.reference_gain(mode, rate)
+                    .filter(|&g| g.is_finite() && g > 0.0)
+                    .map(|g| g.recip())
+                    .unwrap_or(1.0);
+                self.makeup = Some((mode, gain));
+                gain
+            }
+        }
+    }
+
+    /// Designs a filter from its passband and stopband edges, with the lowest order that meets the ripple and attenuation.
+    /// Two edges of each give a band-pass or band-stop, and one of each a low-pass or high-pass.
+    fn design_edges<const F: usize>(kind: FilterKind, fp: [f64; F], fs: [f64; F], rp: f64, rs: f64, rate: f64) -> Result<SpecfilterSos, Box<dyn std::error::Error>>
+    where
+        [(); F - 1]:,
+        [(); 2 - F]:
+    {
+        let plane = FilterGenPlane::Z { sampling_frequency: Some(rate) };
+        let h = match kind
+        {
+            FilterKind::Butterworth => {
+                let (n, wp, ws, t) = signal_processing::gen::filter::buttord(fp, fs, rp, rs, plane)?;
+                let w = wp.comap(ws, |wp, ws| (wp + ws)*0.5);
+                Zpk::butter(n.min(MAX_ORDER), w, t, FilterGenPlane::Z { sampling_frequency: None })
+            },
+            FilterKind::Chebyshev1 => {
+                let (n, wp, ws, rp, t) = signal_processing::gen::filter::cheb1ord(fp, fs, rp, rs, plane)?;
+                let w = wp.comap(ws, |wp, ws| (wp + ws)*0.5);
+                Zpk::cheby1(n.min(MAX_ORDER), rp, w, t, FilterGenPlane::Z { sampling_frequency: None })
+            },
+            FilterKind::Chebyshev2 => {
+                let (n, wp, ws, rs, t) = signal_processing::gen::filter::cheb2ord(fp, fs, rp, rs, plane)?;
+                let w = wp.comap(ws, |wp, ws| (wp + ws)*0.5);
+                Zpk::cheby2(n.min(MAX_ORDER), rs, w, t, FilterGenPlane::Z { sampling_frequency: None })
+            },
+            FilterKind::Elliptic => {
+                let (n, wp, ws, rp, rs, t) = signal_processing::gen::filter::ellipord(fp, fs, rp, rs, plane)?;
+                let w = wp.comap(ws, |wp, ws| (wp + ws)*0.5);
+                Zpk::ellip(n.min(MAX_ORDER), rp, rs, w, t, FilterGenPlane::Z { sampling_frequency: None })
+            }
+        };
+        match h
+        {
+            Ok(h) => Ok(h.stabilize(Plane::Z).to_sos((), ())),
+            Err(FilterGenError::ZeroOrder) => Ok(Sos::one()),
+            Err(err) => Err(err)?
+        }
+    }
+
+    pub fn design(data: &SpecfilterParamData, rate: f64) -> Result<SpecfilterSos, Box<dyn std::error::Error>>
+    {
+        let mut freq = data.frequencies(rate as f32);
+        let rp = data.passband_ripple as f64;
+        let rs = data.stopband_attenuation as f64;
+
+        if let Ok(Ok(freq)) = &mut freq
+        {
+            freq.0.sort_by(TotalOrder::total_cmp);
+            freq.1.sort_by(TotalOrder::total_cmp);
+        }
+
+        let filter = match freq
+        {
+            Ok(Ok((fp, fs))) => Self::design_edges(data.filter_kind, fp, fs, rp, rs, rate)?,
+            Ok(Err((fp, fs))) => Self::design_edges(data.filter_kind, fp, fs, rp, rs, rate)?,
+            Err(pass) => if pass
+            {
+                Sos::one()
+            }
+            else
+            {
+                Sos::new(vec![Tf::new([0.0; 3], [0.0, 0.0, 1.0])])
+            }
+        };
+
+        if filter.sos.iter()
+            .any(|sos| sos.a.iter().any(|a| !a.is_finite()) || sos.b.iter().any(|b| !b.is_finite()))
+            || filter.sos.iter().any(|sos| sos.a.is_zero())
+        {
+            return Err("The designed filter has invalid coefficients".into())
+        }
+
+        Ok(filter)
+    }
+
+    /// Redesigns the filter if its spec has changed.
+    /// Filters designed for the other channels in the same update are passed in `designs`, and reused if the spec matches.
+    pub fn generate_filter(&mut self, data: SpecfilterParamData, rate: f64, designs: &mut Vec<(SpecfilterParamData, SpecfilterSos)>) -> Result<(), Box<dyn std::error::Error>>
+    {
+        if self.param == Some(data)
+        {
+            return Ok(())
+        }
+
+        let filter = match designs.iter()
+            .find(|(designed, _)| *designed == data)
+        {
+            Some((_, filter)) => filter.clone(),
+            None => {
+                let filter = Self::design(&data, rate)?;
+                designs.push((data, filter.clone()));
+                filter
+            }
+        };
+
+        let gain = (filter.sos.iter()
+                .map(|sos| sos.b.trim_zeros_front()
+                        .iter()
+                        .copied()
+                        .map(|b| b*b)
+                        .sum::<f64>()
+                    /sos.a.trim_zeros_front()
+                        .iter()
+                        .copied()
+                        .map(|a| a*a)
+                        .sum::<f64>()
+                ).product::<f64>()
+            /self.filter.sys.sos.iter()
+                .map(|sos| sos.b.trim_zeros_front()
+                        .iter()
+                        .copied()
+                        .map(|b| b*b)
+                        .sum::<f64>()
+                    /sos.a.trim_zeros_front()
+                        .iter()
+                        .copied()
+                        .map(|a| a*a)
+                        .sum::<f64>()
+                ).product::<f64>()).sqrt();
+        let filter_type = data.filter_type(rate as f32);
+        if gain.is_finite() && filter.filtord() == self.filter.sys.filtord() && filter_type != FilterType::NoPass && !(filter_type == FilterType::BandPass && self.filter_type == FilterType::BandStop)
+        {
+            for w in self.filter.w.iter_mut()
+            {
+                *w *= gain
+            }
+        }
+        else
+        {
+            self.filter.w.clear()
+        }
+
+        self.allpass.update(&filter);
+        self.filter_tail = Self::filter_tail(&filter);
+        self.filter.sys = filter;
+        self.filter_type = filter_type;
+        self.param = Some(data);
+        self.makeup = None;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn test_tail()
+{
+    // A third-order cascade, where the slowest pole is the real one in the first-order section
+    let sos: SpecfilterSos = Sos::new(vec![
+        Tf::new([0.0, 0.0, 1.0], [1.0, -0.5, 0.25]),
+        Tf::new([0.0, 0.0, 0.01], [0.0, 1.0, -0.99])
+    ]);
+    let expected = TAIL_DB/20.0*core::f64::consts::LN_10/-0.99f64.ln();
+    assert!((SpecfilterChannel::filter_tail(&sos) - expected).abs() < 1e-6, "The real pole of an odd-order filter is missed");
+}
\ No newline at end of file
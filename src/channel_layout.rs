@@ -0,0 +1,89 @@
+use core::fmt::Display;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ChannelLayout
+{
+    Mono,
+    Stereo,
+    Lcr,
+    Surround51,
+    Surround71
+}
+
+/// The start of a VstSpeakerArrangement, which is all that is needed to tell the layouts apart.
+#[repr(C)]
+pub struct SpeakerArrangement
+{
+    pub arrangement_type: i32,
+    pub num_channels: i32
+}
+
+impl ChannelLayout
+{
+    pub const VARIANT_COUNT: usize = core::mem::variant_count::<Self>();
+    pub const VARIANTS: [Self; Self::VARIANT_COUNT] = [
+        Self::Mono,
+        Self::Stereo,
+        Self::Lcr,
+        Self::Surround51,
+        Self::Surround71
+    ];
+    pub const VARIANT_NAMES: [&'static str; Self::VARIANT_COUNT] = [
+        "Mono",
+        "Stereo",
+        "LCR",
+        "5.1",
+        "7.1"
+    ];
+    pub fn channel_count(&self) -> usize
+    {
+        match self
+        {
+            Self::Mono => 1,
+            Self::Stereo => 2,
+            Self::Lcr => 3,
+            Self::Surround51 => 6,
+            Self::Surround71 => 8
+        }
+    }
+
+    /// Index of the LFE channel, in the order L R C LFE Ls Rs Lss Rss used by VST.
+    pub fn lfe(&self) -> Option<usize>
+    {
+        match self
+        {
+            Self::Surround51 | Self::Surround71 => Some(3),
+            _ => None
+        }
+    }
+
+    /// Which side a channel is on, -1 for left, 1 for right and 0 for the center, LFE and mono channels.
+    pub fn side(&self, channel: usize) -> f32
+    {
+        match self
+        {
+            Self::Mono => 0.0,
+            Self::Stereo => [-1.0, 1.0][channel],
+            _ => [-1.0, 1.0, 0.0, 0.0, -1.0, 1.0, -1.0, 1.0][channel]
+        }
+    }
+
+    pub fn channel_name(&self, channel: usize) -> &'static str
+    {
+        match self
+        {
+            Self::Mono => "M",
+            Self::Stereo => ["L", "R"][channel],
+            _ => ["L", "R", "C", "LFE", "Ls", "Rs", "Lss", "Rss"][channel]
+        }
+    }
+}
+
+impl Display for ChannelLayout
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", Self::VARIANT_NAMES[*self as usize])
+    }
+}
\ No newline at end of file
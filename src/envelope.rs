@@ -0,0 +1,58 @@
+use std::ops::Range;
+
+use crate::envelope_detector::EnvelopeDetector;
+
+#[derive(Clone, Copy)]
+pub struct EnvelopeFollower
+{
+    level: f64
+}
+
+impl EnvelopeFollower
+{
+    pub fn new() -> Self
+    {
+        Self {
+            level: 0.0
+        }
+    }
+
+    pub fn reset(&mut self)
+    {
+        self.level = 0.0
+    }
+
+    /// Follows the level of all channels over the block, and returns the level at the end of it.
+    /// Attack and release are given in seconds.
+    pub fn process(&mut self, x: &[Vec<f64>], block: Range<usize>, detector: EnvelopeDetector, attack: f32, release: f32, rate: f64) -> f32
+    {
+        let attack = (-1.0/(attack as f64*rate)).exp();
+        let release = (-1.0/(release as f64*rate)).exp();
+
+        for i in block
+        {
+            let x = match detector
+            {
+                EnvelopeDetector::Peak => x.iter()
+                    .map(|x| x[i].abs())
+                    .fold(0.0, f64::max),
+                EnvelopeDetector::Rms => x.iter()
+                    .map(|x| x[i]*x[i])
+                    .sum::<f64>()/x.len().max(1) as f64
+            };
+            let coeff = if x > self.level {attack} else {release};
+            self.level = x + (self.level - x)*coeff;
+        }
+
+        if !self.level.is_finite()
+        {
+            self.level = 0.0
+        }
+
+        match detector
+        {
+            EnvelopeDetector::Peak => self.level as f32,
+            EnvelopeDetector::Rms => self.level.sqrt() as f32
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,30 @@
+use core::fmt::Display;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum EnvelopeDetector
+{
+    Peak,
+    Rms
+}
+
+impl EnvelopeDetector
+{
+    pub const VARIANT_COUNT: usize = core::mem::variant_count::<Self>();
+    pub const VARIANTS: [Self; Self::VARIANT_COUNT] = [
+        Self::Peak,
+        Self::Rms
+    ];
+    pub const VARIANT_NAMES: [&'static str; Self::VARIANT_COUNT] = [
+        "Peak",
+        "RMS"
+    ];
+}
+
+impl Display for EnvelopeDetector
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", Self::VARIANT_NAMES[*self as usize])
+    }
+}
\ No newline at end of file
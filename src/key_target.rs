@@ -0,0 +1,36 @@
+use core::fmt::Display;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum KeyTarget
+{
+    Off,
+    Frequency1,
+    Frequency2,
+    Center
+}
+
+impl KeyTarget
+{
+    pub const VARIANT_COUNT: usize = core::mem::variant_count::<Self>();
+    pub const VARIANTS: [Self; Self::VARIANT_COUNT] = [
+        Self::Off,
+        Self::Frequency1,
+        Self::Frequency2,
+        Self::Center
+    ];
+    pub const VARIANT_NAMES: [&'static str; Self::VARIANT_COUNT] = [
+        "Off",
+        "Frequency 1",
+        "Frequency 2",
+        "Center"
+    ];
+}
+
+impl Display for KeyTarget
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", Self::VARIANT_NAMES[*self as usize])
+    }
+}
\ No newline at end of file
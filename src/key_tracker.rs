@@ -0,0 +1,101 @@
+use crate::key_target::KeyTarget;
+use crate::parameters::{SpecfilterParamData, SpecfilterParameters};
+use crate::units;
+
+const NOTE_OFF: u8 = 0x80;
+const NOTE_ON: u8 = 0x90;
+const CONTROL_CHANGE: u8 = 0xB0;
+const PITCH_BEND: u8 = 0xE0;
+const ALL_NOTES_OFF: u8 = 123;
+
+/// Follows the incoming MIDI notes with last-note priority.
+/// The last played note is kept after release, so the filter does not jump back while the sound rings out.
+#[derive(Clone)]
+pub struct KeyTracker
+{
+    held: Vec<(u8, u8)>,
+    last: Option<(u8, u8)>,
+    bend: f32
+}
+
+impl KeyTracker
+{
+    pub fn new() -> Self
+    {
+        Self {
+            held: vec![],
+            last: None,
+            bend: 0.0
+        }
+    }
+
+    /// Follows a MIDI event, and returns whether it could have changed the tracked note.
+    pub fn event(&mut self, data: [u8; 3]) -> bool
+    {
+        match data[0] & 0xF0
+        {
+            NOTE_ON if data[2] > 0 => {
+                self.held.retain(|&(note, _)| note != data[1]);
+                self.held.push((data[1], data[2]));
+                self.last = Some((data[1], data[2]));
+            },
+            NOTE_ON | NOTE_OFF => {
+                self.held.retain(|&(note, _)| note != data[1]);
+                if let Some(&held) = self.held.last()
+                {
+                    self.last = Some(held)
+                }
+            },
+            CONTROL_CHANGE if data[1] == ALL_NOTES_OFF => self.held.clear(),
+            PITCH_BEND => self.bend = ((data[1] as u16 | (data[2] as u16) << 7) as f32 - 8192.0)/8192.0,
+            _ => return false
+        }
+        true
+    }
+
+    pub fn reset(&mut self)
+    {
+        *self = Self::new()
+    }
+
+    /// The current note, including pitch bend, and its velocity in the range 0.0 to 1.0.
+    pub fn note(&self, bend_range: f32) -> Option<(f32, f32)>
+    {
+        let (note, velocity) = self.last?;
+        Some((note as f32 + self.bend*bend_range, velocity as f32/127.0))
+    }
+
+    pub fn track(&self, param: &SpecfilterParameters, data: &mut SpecfilterParamData, rate: f32)
+    {
+        let target = param.key_target();
+        if target == KeyTarget::Off
+        {
+            return
+        }
+        let Some((note, velocity)) = self.note(param.pitch_bend_range.get())
+        else
+        {
+            return
+        };
+
+        let amount = param.key_tracking.get();
+        let f = units::note_frequency(note).log2();
+        match target
+        {
+            KeyTarget::Off => (),
+            KeyTarget::Frequency1 => data.shift_octaves([amount*(f - data.frequencies[0].log2()), 0.0], rate),
+            KeyTarget::Frequency2 => data.shift_octaves([0.0, amount*(f - data.frequencies[1].log2())], rate),
+            KeyTarget::Center => if let Some(center) = data.center(rate)
+            {
+                let octaves = amount*(f - center.log2());
+                data.shift_octaves([octaves; 2], rate)
+            }
+        }
+
+        let velocity_scale = 1.0 - param.velocity_bandwidth.get()*(1.0 - velocity);
+        for bw in data.bandwidths.iter_mut()
+        {
+            *bw *= velocity_scale
+        }
+    }
+}
\ No newline at end of file
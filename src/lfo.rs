@@ -0,0 +1,111 @@
+use vst::api::{TimeInfo, TimeInfoFlags};
+
+use crate::parameters::SpecfilterParameters;
+
+/// Tempo-synced LFO divisions, as names and lengths in quarter notes.
+pub const LFO_DIVISION_COUNT: usize = 9;
+pub const LFO_DIVISIONS: [(&'static str, f64); LFO_DIVISION_COUNT] = [
+    ("1/32", 0.125),
+    ("1/16", 0.25),
+    ("1/8", 0.5),
+    ("1/4", 1.0),
+    ("1/2", 2.0),
+    ("1 bar", 4.0),
+    ("2 bars", 8.0),
+    ("4 bars", 16.0),
+    ("8 bars", 32.0)
+];
+
+pub struct Lfo
+{
+    phase: f64,
+    tempo: f64,
+    /// The song position in quarter notes at the start of the host buffer, and the sample position it was taken at, while the transport is playing.
+    anchor: Option<(f64, u64)>
+}
+
+impl Lfo
+{
+    pub fn new() -> Self
+    {
+        Self {
+            phase: 0.0,
+            tempo: 120.0,
+            anchor: None
+        }
+    }
+
+    pub fn reset(&mut self)
+    {
+        self.phase = 0.0;
+        self.anchor = None;
+    }
+
+    /// Takes the tempo from the host, and while the transport is playing, the song position at the start of the buffer, which is at sample `position`.
+    /// A synced LFO locks its phase to the song position, carried forward to each point on the control grid.
+    /// The song position is only taken again when it jumps, so that the phase does not depend on where the host buffers start.
+    pub fn sync(&mut self, param: &SpecfilterParameters, time_info: Option<TimeInfo>, position: u64, rate: f64)
+    {
+        let Some(time_info) = time_info
+        else
+        {
+            self.anchor = None;
+            return
+        };
+        let flags = TimeInfoFlags::from_bits_truncate(time_info.flags);
+
+        if flags.contains(TimeInfoFlags::TEMPO_VALID) && time_info.tempo > 0.0
+        {
+            self.tempo = time_info.tempo
+        }
+        if !param.lfo_sync() || !flags.contains(TimeInfoFlags::TRANSPORT_PLAYING | TimeInfoFlags::PPQ_POS_VALID)
+        {
+            self.anchor = None;
+            return
+        }
+        let quarters_per_sample = self.tempo/60.0/rate;
+        let continues = self.anchor.is_some_and(|(ppq_pos, start)| {
+            let predicted = ppq_pos + (position as f64 - start as f64)*quarters_per_sample;
+            (predicted - time_info.ppq_pos).abs() < quarters_per_sample
+        });
+        if !continues
+        {
+            self.anchor = Some((time_info.ppq_pos, position))
+        }
+    }
+
+    pub fn frequency(&self, param: &SpecfilterParameters) -> f64
+    {
+        if param.lfo_sync()
+        {
+            self.tempo/60.0/param.lfo_division().1
+        }
+        else
+        {
+            param.lfo_rate.get() as f64
+        }
+    }
+
+    /// Advances the LFO by the `len` samples since the last call, to sample `position`, and returns the octave offset for each channel there.
+    pub fn next(&mut self, param: &SpecfilterParameters, channels: usize, position: u64, len: usize, rate: f64) -> Vec<f32>
+    {
+        if let Some((ppq_pos, start)) = self.anchor.filter(|_| param.lfo_sync())
+        {
+            let ppq_pos = ppq_pos + (position as f64 - start as f64)*self.tempo/60.0/rate;
+            self.phase = ppq_pos/param.lfo_division().1
+        }
+
+        else
+        {
+            self.phase += len as f64*self.frequency(param)/rate;
+        }
+
+        let shape = param.lfo_shape();
+        let depth = param.lfo_depth.get();
+        let stereo_phase = param.lfo_stereo_phase.get() as f64;
+
+        (0..channels)
+            .map(|i| depth*shape.value(self.phase + stereo_phase*i as f64))
+            .collect()
+    }
+}
\ No newline at end of file
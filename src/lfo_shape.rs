@@ -0,0 +1,75 @@
+use core::fmt::Display;
+use std::f64::consts::{PI, TAU};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum LfoShape
+{
+    Sine,
+    Triangle,
+    Saw,
+    Square,
+    SampleAndHold,
+    SmoothRandom
+}
+
+impl LfoShape
+{
+    pub const VARIANT_COUNT: usize = core::mem::variant_count::<Self>();
+    pub const VARIANTS: [Self; Self::VARIANT_COUNT] = [
+        Self::Sine,
+        Self::Triangle,
+        Self::Saw,
+        Self::Square,
+        Self::SampleAndHold,
+        Self::SmoothRandom
+    ];
+    pub const VARIANT_NAMES: [&'static str; Self::VARIANT_COUNT] = [
+        "Sine",
+        "Triangle",
+        "Saw",
+        "Square",
+        "Sample and hold",
+        "Smooth random"
+    ];
+
+    /// A pseudo-random value in the range -1.0 to 1.0 for the given cycle.
+    /// It only depends on the cycle, so that phase-offset channels see the same sequence.
+    fn random(cycle: f64) -> f64
+    {
+        let mut z = (cycle as i64 as u64).wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        (z >> 11) as f64/(1u64 << 53) as f64*2.0 - 1.0
+    }
+
+    /// The value of the waveform in the range -1.0 to 1.0, where the phase is given in cycles.
+    pub fn value(&self, phase: f64) -> f32
+    {
+        let cycle = phase.floor();
+        let t = phase - cycle;
+
+        (match self
+        {
+            LfoShape::Sine => (TAU*t).sin(),
+            LfoShape::Triangle => 1.0 - 4.0*((t + 0.25).fract() - 0.5).abs(),
+            LfoShape::Saw => 2.0*t - 1.0,
+            LfoShape::Square => if t < 0.5 {1.0} else {-1.0},
+            LfoShape::SampleAndHold => Self::random(cycle),
+            LfoShape::SmoothRandom => {
+                let a = Self::random(cycle);
+                let b = Self::random(cycle + 1.0);
+                a + (b - a)*(1.0 - (PI*t).cos())*0.5
+            }
+        }) as f32
+    }
+}
+
+impl Display for LfoShape
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", Self::VARIANT_NAMES[*self as usize])
+    }
+}
\ No newline at end of file
@@ -6,9 +6,11 @@
 #![feature(generic_const_exprs)]
 
 use std::f32::EPSILON;
+use std::ops::Range;
 use std::f32::consts::TAU;
 use std::process::Command;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::ffi::c_void;
 use std::sync::atomic::{Ordering, AtomicU8};
 
 use array_math::{ArrayOps, SliceMath};
@@ -23,7 +25,23 @@ use signal_processing::systems::{Rtf, Sos, Tf, Zpk};
 use signal_processing::transforms::filter::Stabilize;
 use signal_processing::transforms::system::ToSos;
 use signal_processing::Plane;
+use preset::FACTORY_PRESET_COUNT;
 use tube_stage::TubeStage;
+use key_tracker::KeyTracker;
+use channel::SpecfilterChannel;
+use channel_layout::{ChannelLayout, SpeakerArrangement};
+use stereo_mode::StereoMode;
+use spec_group::SpecGroup;
+use analysis::MonoAnalyzer;
+use lfo::Lfo;
+use smoothed::Smoothed;
+use output_mode::OutputMode;
+use envelope::EnvelopeFollower;
+use sidechain::{SidechainDetector, SIDECHAIN_CHANNEL_COUNT};
+use pitch_tracker::PitchTracker;
+use vst::api::{AEffect, DispatcherProc, TimeInfoFlags};
+use vst::plugin::OpCode;
+use vst::host::{Host, OpCode as HostOpCode};
 use vst::{prelude::*, plugin_main};
 
 use crate::filter_kind::FilterKind;
@@ -34,22 +52,122 @@ pub mod parameters;
 pub mod filter_type;
 pub mod filter_kind;
 pub mod tube_stage;
-
-const CHANGE: f32 = 2000.0;
-const MAX_ORDER: usize = 64;
+pub mod preset;
+pub mod units;
+pub mod key_target;
+pub mod key_tracker;
+pub mod midi_map;
+pub mod channel;
+pub mod lfo;
+pub mod lfo_shape;
+pub mod envelope;
+pub mod envelope_detector;
+pub mod sidechain;
+pub mod sidechain_mode;
+pub mod pitch_tracker;
+pub mod pitch_target;
+pub mod bandwidth_unit;
+pub mod response_type;
+pub mod spec_mode;
+pub mod makeup_mode;
+pub mod allpass;
+pub mod output_mode;
+pub mod channel_layout;
+pub mod stereo_mode;
+pub mod smoothed;
+pub mod spec_group;
+pub mod link_mode;
+pub mod analysis;
+pub mod tube_preset;
 
 struct SpecfilterPlugin
 {
     pub param: Arc<SpecfilterParameters>,
-    param_prev: Option<SpecfilterParamData>,
-    filter_type: FilterType,
-    filter: [Rtf<f64, Sos<f64, [f64; 3], [f64; 3], Vec<Tf<f64, [f64; 3], [f64; 3]>>>>; CHANNEL_COUNT],
-    tubes: [TubeStage; CHANNEL_COUNT],
+    param_prev: [Option<SpecfilterParamData>; SpecGroup::VARIANT_COUNT],
+    param_valid: [Option<SpecfilterParamData>; SpecGroup::VARIANT_COUNT],
+    channels: Vec<SpecfilterChannel>,
+    layout: ChannelLayout,
+    key_tracker: KeyTracker,
+    lfo: Lfo,
+    envelope: EnvelopeFollower,
+    envelope_level: f32,
+    sidechain: SidechainDetector,
+    sidechain_level: f32,
+    pitch_tracker: PitchTracker,
+    mono_analyzer: MonoAnalyzer,
+    /// Input gain, output gain and mix, smoothed per sample so that they do not step between blocks.
+    gains: [Smoothed; 3],
+    events: Vec<(usize, [u8; 3])>,
+    position: u64,
+    /// The sample position of the last control update.
+    last_update: u64,
+    /// Set when a MIDI event moves the tracked note, so that the next block updates the filters right away instead of waiting for the control grid.
+    key_changed: bool,
+    bypass_fade: f64,
     rate: f64,
     host: HostCallback
 }
 
-const CHANNEL_COUNT: usize = 2;
+/// Crossfade time in seconds when entering or leaving bypass.
+const BYPASS_FADE: f64 = 0.02;
+
+/// The dispatcher of the vst crate, which does not forward the soft bypass opcode to the plugin.
+static DISPATCHER: OnceLock<DispatcherProc> = OnceLock::new();
+
+/// Number of inputs and outputs for a layout.
+/// The inputs are the main channels followed by the sidechain, and the outputs are the main channels followed by the rejected content in split mode.
+fn io_count(layout: ChannelLayout) -> (i32, i32)
+{
+    let channels = layout.channel_count();
+    ((channels + SIDECHAIN_CHANNEL_COUNT) as i32, (channels*2) as i32)
+}
+
+/// Handles soft bypass by setting the bypass parameter, and speaker arrangements by setting the channel layout.
+/// Everything else is passed on to the dispatcher of the vst crate.
+extern "C" fn dispatch(effect: *mut AEffect, opcode: i32, index: i32, value: isize, ptr: *mut c_void, opt: f32) -> isize
+{
+    if opcode == OpCode::SoftBypass as i32
+    {
+        let param = unsafe {(*effect).get_params()};
+        param.set_parameter(SpecfilterParam::Bypass as i32, (value != 0) as u8 as f32);
+        return 1
+    }
+    if opcode == OpCode::SetSpeakerArrangement as i32
+    {
+        let input = value as *const SpeakerArrangement;
+        let output = ptr as *const SpeakerArrangement;
+        if input.is_null() || output.is_null()
+        {
+            return 0
+        }
+        let (input, output) = unsafe {((*input).num_channels, (*output).num_channels)};
+        // Only the main bus is negotiated. Hosts describe either the main bus alone, or every pin including the sidechain inputs and split outputs
+        let Some(layout) = ChannelLayout::VARIANTS.into_iter()
+            .find(|layout| (input, output) == (layout.channel_count() as i32, layout.channel_count() as i32))
+            .or_else(|| ChannelLayout::VARIANTS.into_iter()
+                .find(|&layout| (input, output) == io_count(layout))
+            )
+        else
+        {
+            return 0
+        };
+        unsafe {
+            let param = (*effect).get_params();
+            param.set_parameter(SpecfilterParam::ChannelLayout as i32, layout as u8 as f32/(ChannelLayout::VARIANT_COUNT - 1) as f32);
+            // The host is suspended while negotiating, and picks up the new counts before resuming
+            ((*effect).numInputs, (*effect).numOutputs) = io_count(layout);
+        }
+        return 1
+    }
+    match DISPATCHER.get()
+    {
+        Some(dispatcher) => dispatcher(effect, opcode, index, value, ptr, opt),
+        None => 0
+    }
+}
+const CONTROL_CHANGE: u8 = 0xB0;
+/// Parameter updates and filter redesigns happen every this many samples, counted from the start of playback, regardless of the host's buffer size.
+const CONTROL_BLOCK: usize = 32;
 
 #[test]
 fn test()
@@ -64,246 +182,239 @@ fn test()
     println!("Ok!")
 }
 
-impl SpecfilterPlugin
+#[test]
+fn test_block_size()
 {
-    fn generate_filter(&mut self, buf_len: usize) -> Result<(), Box<dyn std::error::Error>>
-    {
-        self.param.rate.set(self.rate as f32);
-
-        let param_next: SpecfilterParamData = (&*self.param).into();
-        if let Some(param_prev) = &mut self.param_prev
+    let len = 4096;
+    let x: Vec<Vec<f64>> = (0..ChannelLayout::Stereo.channel_count())
+        .map(|c| (0..len)
+            .map(|n| (n as f64*0.01*(c + 1) as f64).sin() + ((n*7919 + c*104729) % 1000) as f64/1000.0 - 0.5)
+            .collect()
+        ).collect();
+
+    let render = |block_len: usize, sync: bool| {
+        let mut plugin = SpecfilterPlugin::new(HostCallback::default());
+
+        plugin.param.frequencies[0].set(200.0);
+        plugin.param.frequencies[1].set(3000.0);
+        plugin.param.lfo_depth.set(1.0);
+        plugin.param.lfo_sync.store(sync, Ordering::Relaxed);
+        plugin.param.envelope_depth.set(1.0);
+
+        let mut y = vec![vec![]; x.len()*2];
+        for start in (0..len).step_by(block_len)
         {
-            if &param_next == param_prev
-            {
-                return Ok(())
-            }
-            if param_prev.filter_kind != param_next.filter_kind
+            // A host playing from beat 3 at 120 BPM, which reports the song position at the start of each buffer
+            let time_info = vst::api::TimeInfo {
+                ppq_pos: 3.0 + start as f64/plugin.rate*2.0,
+                tempo: 120.0,
+                flags: (TimeInfoFlags::TRANSPORT_PLAYING | TimeInfoFlags::TEMPO_VALID | TimeInfoFlags::PPQ_POS_VALID).bits(),
+                ..Default::default()
+            };
+            plugin.lfo.sync(&plugin.param, Some(time_info), plugin.position, plugin.rate);
+            let end = (start + block_len).min(len);
+            let x: Vec<Vec<f64>> = x.iter()
+                .map(|x| x[start..end].to_vec())
+                .collect();
+            for (y, z) in y.iter_mut()
+                .zip(plugin.process_channels(&x, &[]))
             {
-                for rtf in self.filter.iter_mut()
-                {
-                    rtf.w.clear()
-                }
+                y.extend(z)
             }
-            param_prev.change(param_next, 1.0 - (-CHANGE*buf_len as f32/(self.filter[0].sys.filtord() + 1) as f32/self.rate as f32).exp());
         }
-        else
+        y
+    };
+
+    for sync in [false, true]
+    {
+        let reference = render(64, sync);
+        for block_len in [1, 100, 2048]
         {
-            self.param_prev = Some(param_next)
+            assert!(render(block_len, sync) == reference, "Output differs at block size {} with sync {}", block_len, sync);
         }
-        let param_data = self.param_prev.as_ref()
-            .unwrap();
+    }
+}
 
-        let mut freq = param_data.frequencies(self.rate as f32);
-        let rp = param_data.passband_ripple as f64;
-        let rs = param_data.stopband_attenuation as f64;
+#[test]
+fn test_lfe()
+{
+    let len = 1024;
+    let mut plugin = SpecfilterPlugin::new(HostCallback::default());
+
+    plugin.param.channel_layout.store(ChannelLayout::Surround51 as u8, Ordering::Relaxed);
+    plugin.update_layout();
+
+    let x: Vec<Vec<f64>> = (0..ChannelLayout::Surround51.channel_count())
+        .map(|c| (0..len)
+            .map(|n| (n as f64*0.01*(c + 1) as f64).sin())
+            .collect()
+        ).collect();
+    let y = plugin.process_channels(&x, &[]);
+
+    assert!(y.len() == x.len()*2);
+    assert!(y[3] == x[3], "LFE is not passed through");
+    assert!(y[0] != x[0]);
+
+    // The output gain still applies to the bypassed LFE channel
+    plugin.param.output_gain.set(-6.0);
+    plugin.gains.iter_mut()
+        .for_each(|gain| gain.reset());
+    let y = plugin.process_channels(&x, &[]);
+    let output_gain = units::db_to_gain(-6.0) as f64;
+    assert!(y[3].iter()
+        .zip(&x[3])
+        .all(|(&y, &x)| (y - x*output_gain).abs() < 1e-12), "Output gain is not applied to the LFE channel");
+}
 
-        if let Ok(Ok(freq)) = &mut freq
+#[test]
+fn test_preset_rate()
+{
+    let load = |rate: f32, chunk: Option<&[u8]>| {
+        let mut plugin = SpecfilterPlugin::new(HostCallback::default());
+        plugin.set_sample_rate(rate);
+        match chunk
         {
-            freq.0.sort_by(TotalOrder::total_cmp);
-            freq.1.sort_by(TotalOrder::total_cmp);
+            Some(chunk) => plugin.param.load_preset_data(chunk),
+            None => plugin.param.change_preset(1)
         }
+        [plugin.param.frequencies[0].get(), plugin.param.frequencies[1].get()]
+    };
+
+    let at_48k = load(48000.0, None);
+    assert!(at_48k == load(96000.0, None), "Factory preset lands on different frequencies");
 
-        let filter = match freq
+    let mut plugin = SpecfilterPlugin::new(HostCallback::default());
+    plugin.set_sample_rate(48000.0);
+    plugin.param.change_preset(1);
+    let chunk = plugin.param.get_preset_data();
+    assert!(load(96000.0, Some(&chunk)) == at_48k, "Preset chunk lands on different frequencies");
+}
+
+impl SpecfilterPlugin
+{
+    /// Switches to the channel layout set in the parameters, and tells the host if its input and output counts no longer match.
+    /// This takes effect when the host resumes processing, as the buffers it passes must already match the new layout.
+    fn update_layout(&mut self)
+    {
+        let layout = self.param.channel_layout();
+        if layout == self.layout
         {
-            Ok(freq) => match match self.param.filter_kind()
-            {
-                FilterKind::Butterworth => {
-                    match freq
-                    {
-                        Ok((fp, fs)) => {
-                            let (n, wp, ws, t) = signal_processing::gen::filter::buttord(
-                                fp,
-                                fs,
-                                rp,
-                                rs,
-                                FilterGenPlane::Z { sampling_frequency: Some(self.rate) }
-                            )?;
-                            let n = n.min(MAX_ORDER);
-                            let w = wp.comap(ws, |wp, ws| (wp + ws)*0.5);
-                            Zpk::butter(n, w, t, FilterGenPlane::Z { sampling_frequency: None })
-                        },
-                        Err((fp, fs)) => {
-                            let (n, wp, ws, t) = signal_processing::gen::filter::buttord(
-                                fp,
-                                fs,
-                                rp,
-                                rs,
-                                FilterGenPlane::Z { sampling_frequency: Some(self.rate) }
-                            )?;
-                            let n = n.min(MAX_ORDER);
-                            let w = wp.comap(ws, |wp, ws| (wp + ws)*0.5);
-                            Zpk::butter(n, w, t, FilterGenPlane::Z { sampling_frequency: None })
-                        }
-                    }
-                },
-                FilterKind::Chebyshev1 => {
-                    match freq
-                    {
-                        Ok((fp, fs)) => {
-                            let (n, wp, ws, rp, t) = signal_processing::gen::filter::cheb1ord(
-                                fp,
-                                fs,
-                                rp,
-                                rs,
-                                FilterGenPlane::Z { sampling_frequency: Some(self.rate) }
-                            )?;
-                            let n = n.min(MAX_ORDER);
-                            let w = wp.comap(ws, |wp, ws| (wp + ws)*0.5);
-                            Zpk::cheby1(n, rp, w, t, FilterGenPlane::Z { sampling_frequency: None })
-                        },
-                        Err((fp, fs)) => {
-                            let (n, wp, ws, rp, t) = signal_processing::gen::filter::cheb1ord(
-                                fp,
-                                fs,
-                                rp,
-                                rs,
-                                FilterGenPlane::Z { sampling_frequency: Some(self.rate) }
-                            )?;
-                            let n = n.min(MAX_ORDER);
-                            let w = wp.comap(ws, |wp, ws| (wp + ws)*0.5);
-                            Zpk::cheby1(n, rp, w, t, FilterGenPlane::Z { sampling_frequency: None })
-                        }
-                    }
-                },
-                FilterKind::Chebyshev2 => {
-                    match freq
-                    {
-                        Ok((fp, fs)) => {
-                            let (n, wp, ws, rs, t) = signal_processing::gen::filter::cheb2ord(
-                                fp,
-                                fs,
-                                rp,
-                                rs,
-                                FilterGenPlane::Z { sampling_frequency: Some(self.rate) }
-                            )?;
-                            let n = n.min(MAX_ORDER);
-                            let w = wp.comap(ws, |wp, ws| (wp + ws)*0.5);
-                            Zpk::cheby2(n, rs, w, t, FilterGenPlane::Z { sampling_frequency: None })
-                        },
-                        Err((fp, fs)) => {
-                            let (n, wp, ws, rs, t) = signal_processing::gen::filter::cheb2ord(
-                                fp,
-                                fs,
-                                rp,
-                                rs,
-                                FilterGenPlane::Z { sampling_frequency: Some(self.rate) }
-                            )?;
-                            let n = n.min(MAX_ORDER);
-                            let w = wp.comap(ws, |wp, ws| (wp + ws)*0.5);
-                            Zpk::cheby2(n, rs, w, t, FilterGenPlane::Z { sampling_frequency: None })
-                        }
-                    }
-                },
-                FilterKind::Elliptic => {
-                    match freq
+            return
+        }
+        self.layout = layout;
+        self.channels = (0..layout.channel_count())
+            .map(|_| SpecfilterChannel::new())
+            .collect();
+        self.param_prev = [None; SpecGroup::VARIANT_COUNT];
+        self.param_valid = [None; SpecGroup::VARIANT_COUNT];
+
+        let effect = self.host.raw_effect();
+        if !effect.is_null()
+        {
+            let (inputs, outputs) = io_count(layout);
+            unsafe {
+                if (*effect).numInputs != inputs || (*effect).numOutputs != outputs
+                {
+                    ((*effect).numInputs, (*effect).numOutputs) = (inputs, outputs);
+                    if let Some(callback) = self.host.raw_callback()
                     {
-                        Ok((fp, fs)) => {
-                            let (n, wp, ws, rp, rs, t) = signal_processing::gen::filter::ellipord(
-                                fp,
-                                fs,
-                                rp,
-                                rs,
-                                FilterGenPlane::Z { sampling_frequency: Some(self.rate) }
-                            )?;
-                            let n = n.min(MAX_ORDER);
-                            let w = wp.comap(ws, |wp, ws| (wp + ws)*0.5);
-                            Zpk::ellip(n, rp, rs, w, t, FilterGenPlane::Z { sampling_frequency: None })
-                        },
-                        Err((fp, fs)) => {
-                            let (n, wp, ws, rp, rs, t) = signal_processing::gen::filter::ellipord(
-                                fp,
-                                fs,
-                                rp,
-                                rs,
-                                FilterGenPlane::Z { sampling_frequency: Some(self.rate) }
-                            )?;
-                            let n = n.min(MAX_ORDER);
-                            let w = wp.comap(ws, |wp, ws| (wp + ws)*0.5);
-                            Zpk::ellip(n, rp, rs, w, t, FilterGenPlane::Z { sampling_frequency: None })
-                        }
+                        callback(effect, HostOpCode::IOChanged as i32, 0, 0, std::ptr::null_mut(), 0.0);
                     }
-                },
-            }
-            {
-                Ok(h) => h.stabilize(Plane::Z).to_sos((), ()),
-                Err(err) => match err
-                {
-                    FilterGenError::ZeroOrder => Sos::one(),
-                    _ => Err(err)?
                 }
-            },
-            Err(pass) => if pass
-            {
-                Sos::one()
             }
-            else
+        }
+
+        self.control_update();
+    }
+
+    /// Whether the stereo pair is filtered as mid and side.
+    fn mid_side(&self) -> bool
+    {
+        self.param.stereo_mode() == StereoMode::MidSide && self.channels.len() == 2
+    }
+
+    /// The spec group each channel is filtered with.
+    fn channel_groups(&self) -> Vec<SpecGroup>
+    {
+        if self.channels.len() == 2 && self.param.stereo_mode() != StereoMode::Stereo
+        {
+            vec![SpecGroup::A, SpecGroup::B]
+        }
+        else
+        {
+            vec![SpecGroup::A; self.channels.len()]
+        }
+    }
+
+    fn generate_filter(&mut self, buf_len: usize) -> Result<(), Box<dyn std::error::Error>>
+    {
+        self.param.rate.set(self.rate as f32);
+        self.pitch_tracker.glide(&self.param, buf_len, self.rate);
+
+        // Each group in use is tracked and smoothed on its own, and a group that falls out of use starts over from its knobs
+        let groups = self.channel_groups();
+        let mut param_knobs = [None; SpecGroup::VARIANT_COUNT];
+        for group in SpecGroup::VARIANTS
+        {
+            let slot = &mut self.param_prev[group as usize];
+            if !groups.contains(&group)
             {
-                Sos::new(vec![Tf::new([0.0; 3], [0.0, 0.0, 1.0])])
+                *slot = None;
+                continue
             }
-        };
 
-        /*if filter.is_stable(0.01, Plane::Z)
-        {
-            self.filter = filter;
-        }*/
-
-        if filter.sos.iter()
-            .any(|sos| sos.a.iter().any(|a| !a.is_finite()) || sos.b.iter().any(|b| !b.is_finite()))
-            || filter.sos.iter().any(|sos| sos.a.is_zero())
-        {
-            "a".parse::<f64>()?;
-            return Ok(())
-        }
-
-        let gain = (filter.sos.iter()
-                .map(|sos| sos.b.trim_zeros_front()
-                        .iter()
-                        .copied()
-                        .map(|b| b*b)
-                        .sum::<f64>()
-                    /sos.a.trim_zeros_front()
-                        .iter()
-                        .copied()
-                        .map(|a| a*a)
-                        .sum::<f64>()
-                ).product::<f64>()
-            /self.filter[0].sys.sos.iter()
-                .map(|sos| sos.b.trim_zeros_front()
-                        .iter()
-                        .copied()
-                        .map(|b| b*b)
-                        .sum::<f64>()
-                    /sos.a.trim_zeros_front()
-                        .iter()
-                        .copied()
-                        .map(|a| a*a)
-                        .sum::<f64>()
-                ).product::<f64>()).sqrt();
-        let filter_type = param_data.filter_type(self.rate as f32);
-        if gain.is_finite() && filter.filtord() == self.filter[0].sys.filtord() && filter_type != FilterType::NoPass && !(filter_type == FilterType::BandPass && self.filter_type == FilterType::BandStop)
-        {
-            for rtf in self.filter.iter_mut()
+            let knobs = self.param.spec(group);
+            let mut param_next = knobs;
+            self.key_tracker.track(&self.param, &mut param_next, self.rate as f32);
+            self.pitch_tracker.track(&self.param, &mut param_next, self.rate as f32);
+            if let Some(param_prev) = slot.as_mut()
             {
-                for w in rtf.w.iter_mut()
+                if param_prev.filter_kind != param_next.filter_kind
+                {
+                    for (channel, _) in self.channels.iter_mut()
+                        .zip(groups.iter())
+                        .filter(|&(_, &g)| g == group)
+                    {
+                        channel.filter.w.clear()
+                    }
+                }
+                if &param_next != param_prev
                 {
-                    *w *= gain
+                    param_prev.change(param_next, self.param.smoothing_change(buf_len, self.rate));
                 }
             }
-        }
-        else
-        {
-            for rtf in self.filter.iter_mut()
+            else
             {
-                rtf.w.clear()
+                *slot = Some(param_next)
             }
+            param_knobs[group as usize] = Some(knobs);
         }
 
-        for rtf in self.filter[1..].iter_mut()
+        // Modulation is applied after smoothing, and reaches the filters through the click-free coefficient update
+        let lfo = self.lfo.next(&self.param, self.channels.len(), self.position, buf_len, self.rate);
+        let envelope = (self.envelope_level*units::db_to_gain(self.param.envelope_sensitivity.get())).min(1.0);
+        let envelope_octaves = envelope*self.param.envelope_depth.get();
+        let envelope_bandwidth = 1.0 + envelope*self.param.envelope_bandwidth.get();
+        let sidechain = (self.sidechain_level*units::db_to_gain(self.param.sidechain_sensitivity.get())).min(1.0);
+        let sidechain_octaves = sidechain*self.param.sidechain_depth.get();
+        // Spread pulls the left and right channels apart, which means nothing for mid and side
+        let spread = if self.mid_side() {0.0} else {self.param.spread.get()*0.5};
+        let mut designs = vec![];
+        for (i, ((channel, lfo), group)) in self.channels.iter_mut()
+            .zip(lfo)
+            .zip(groups)
+            .enumerate()
         {
-            rtf.sys = filter.clone()
+            let Some(mut data) = self.param_prev[group as usize]
+            else
+            {
+                continue
+            };
+            data.shift_octaves([lfo + envelope_octaves + sidechain_octaves + spread*self.layout.side(i); 2], self.rate as f32);
+            data.scale_bandwidths(envelope_bandwidth);
+            channel.generate_filter(data, self.rate, &mut designs)?;
         }
-        self.filter[0].sys = filter;
-        self.filter_type = filter_type;
+        self.param_valid = param_knobs;
 
         Ok(())
     }
@@ -312,22 +423,115 @@ impl SpecfilterPlugin
     where
         T: Float
     {
-        let buf_len = buffer.samples();
+        self.lfo.sync(&self.param, self.host.get_time_info((TimeInfoFlags::TEMPO_VALID | TimeInfoFlags::PPQ_POS_VALID).bits()), self.position, self.rate);
+
+        // The parameter object can not reach the host, so parameters moved along with a linked one are reported from here
+        for param_id in self.param.take_linked_changes()
+        {
+            self.host.automate(param_id as i32, self.param.get_parameter(param_id as i32));
+        }
+
+        let (inputs, mut outputs) = buffer.split();
+        let mut x: Vec<Vec<f64>> = inputs.into_iter()
+            .map(|input_channel| input_channel.iter()
+                .map(|&x| x.to_f64().unwrap())
+                .collect()
+            ).collect();
+        // Any inputs past the main channels are the sidechain, which may be left out entirely if the host does not connect it
+        let sidechain = x.split_off(x.len().min(self.channels.len()));
+
+        let y = self.process_channels(&x, &sidechain);
+
+        for (output_channel, y) in outputs.into_iter()
+            .zip(y)
+        {
+            for (y, z) in output_channel.iter_mut()
+                .zip(y)
+            {
+                *y = T::from(z).unwrap();
+            }
+        }
+    }
+
+    /// Processes one host buffer, given as one vector per channel.
+    fn process_channels(&mut self, x: &[Vec<f64>], sidechain: &[Vec<f64>]) -> Vec<Vec<f64>>
+    {
+        let buf_len = x.iter()
+            .map(|x| x.len())
+            .min()
+            .unwrap_or(0);
+        let mut y = vec![vec![0.0; buf_len]; self.channels.len()*2];
+
+        // MIDI events are applied at their sample offsets by splitting the block, and notes update the filters from there
+        let mut events = core::mem::take(&mut self.events);
+        events.sort_by_key(|&(delta, _)| delta);
+        let mut events = events.into_iter()
+            .peekable();
+
+        let mut start = 0;
+        while start < buf_len
+        {
+            while let Some((_, data)) = events.next_if(|&(delta, _)| delta <= start)
+            {
+                self.midi_event(data);
+            }
+            let control_end = start + CONTROL_BLOCK - (self.position % CONTROL_BLOCK as u64) as usize;
+            let end = events.peek()
+                .map(|&(delta, _)| delta)
+                .unwrap_or(buf_len)
+                .min(control_end)
+                .min(buf_len);
+
+            self.process_block(x, sidechain, &mut y, start..end);
+            self.position += (end - start) as u64;
+            start = end;
+        }
+        for (_, data) in events
+        {
+            self.midi_event(data);
+        }
+
+        y
+    }
+
+    fn midi_event(&mut self, data: [u8; 3])
+    {
+        self.key_changed |= self.key_tracker.event(data);
+        if data[0] & 0xF0 == CONTROL_CHANGE
+        {
+            for param_id in self.param.midi_cc(data[1], data[2])
+            {
+                self.host.automate(param_id as i32, self.param.get_parameter(param_id as i32));
+            }
+        }
+    }
 
+    /// Updates the parameters and redesigns the filters, once every control block and whenever a note arrives.
+    /// The smoothing and modulation advance by the samples since the last update, so extra updates do not speed them up.
+    fn control_update(&mut self)
+    {
         let prev_data = self.param_prev;
-        if let Err(error) = self.generate_filter(buf_len)
+        let elapsed = self.position.saturating_sub(self.last_update) as usize;
+        self.last_update = self.position;
+        if let Err(error) = self.generate_filter(elapsed)
         {
             self.param_prev = prev_data;
-            if let Some(prev_data) = prev_data
+            for (group, valid_data) in SpecGroup::VARIANTS.into_iter()
+                .zip(self.param_valid)
             {
-                self.param.reset_to(prev_data);
+                let Some(valid_data) = valid_data
+                else
+                {
+                    continue
+                };
+                self.param.reset_spec(group, valid_data);
                 if let Some(dispatcher) = self.host.raw_callback()
                 {
                     // Calls dispatch in the same way that setParameterAutomated does
                     // see:
                     // opcode audioMasterAutomate is given in https://github.com/R-Tur/VST_SDK_2.4/blob/master/pluginterfaces/vst2.x/aeffect.h
                     // dispatch is given in setParameterAutomated in https://github.com/R-Tur/VST_SDK_2.4/blob/master/public.sdk/source/vst2.x/audioeffect.cpp
-                    for param_id in [SpecfilterParam::Frequency1, SpecfilterParam::Frequency2, SpecfilterParam::Bandwidth1, SpecfilterParam::Bandwidth2]
+                    for param_id in group.band_params()
                     {
                         dispatcher(self.host.raw_effect(), 0, param_id as i32, 0, std::ptr::null_mut(), self.param.get_parameter(param_id as i32));
                     }
@@ -339,15 +543,98 @@ impl SpecfilterPlugin
             ).spawn()
             .expect("Failed to start cmd");
         }
+    }
+
+    fn process_block(&mut self, x: &[Vec<f64>], sidechain: &[Vec<f64>], y: &mut [Vec<f64>], block: Range<usize>)
+    {
+        // The filters are reset once, as the fade starts back in from a full bypass
+        if self.bypass_fade >= 1.0 && !self.param.bypass() && self.param.bypass_reset()
+        {
+            for channel in self.channels.iter_mut()
+            {
+                channel.reset()
+            }
+            self.param_prev = [None; SpecGroup::VARIANT_COUNT];
+        }
+
+        // The detectors run before the filter update, so that it follows the level of the block it filters
+        self.envelope_level = self.envelope.process(
+            x,
+            block.clone(),
+            self.param.envelope_detector(),
+            self.param.envelope_attack.get()/1000.0,
+            self.param.envelope_release.get()/1000.0,
+            self.rate
+        );
+        self.sidechain_level = self.sidechain.process(&self.param, sidechain, block.clone(), self.rate);
+        self.pitch_tracker.process(&self.param, x, block.clone(), self.rate);
+
+        if core::mem::take(&mut self.key_changed) || self.position % CONTROL_BLOCK as u64 == 0
+        {
+            self.control_update();
+        }
         
-        let mix = self.param.mix.get() as f64;
+        let makeup_mode = self.param.makeup_mode();
+        let phase_coherent = self.param.phase_coherent_mix();
+        let output_mode = self.param.output_mode();
+        let tube_character = self.param.tube_character();
+        // The gains glide on the same time constant as the tube character
+        let change = self.param.smoothing_change(1, self.rate) as f64;
+        let targets = [
+            units::db_to_gain(self.param.input_gain.get()) as f64,
+            units::db_to_gain(self.param.output_gain.get()) as f64,
+            self.param.mix.get() as f64
+        ];
+        let [input_gain, output_gain, mix]: [Vec<f64>; 3] = core::array::from_fn(|k| block.clone()
+            .map(|_| self.gains[k].next(targets[k], change))
+            .collect()
+        );
+        let lfe = if self.param.lfe_filter() {None} else {self.layout.lfe()};
+        let mid_side = self.mid_side();
+        let groups = self.channel_groups();
+
+        let x_block: Vec<Vec<f64>> = if mid_side
+        {
+            let (l, r) = (&x[0][block.clone()], &x[1][block.clone()]);
+            vec![
+                l.iter().zip(r).map(|(&l, &r)| (l + r)*0.5).collect(),
+                l.iter().zip(r).map(|(&l, &r)| (l - r)*0.5).collect()
+            ]
+        }
+        else
+        {
+            x.iter()
+                .map(|x| x[block.clone()].to_vec())
+                .collect()
+        };
 
-        for (((input_channel, output_channel), filter), tube) in buffer.zip()
-            .zip(self.filter.iter_mut())
-            .zip(self.tubes.iter_mut())
+        let (y_main, y_reject) = y.split_at_mut(self.channels.len());
+        for (i, ((((x, y), y_reject), channel), group)) in x_block.iter()
+            .zip(y_main.iter_mut())
+            .zip(y_reject.iter_mut())
+            .zip(self.channels.iter_mut())
+            .zip(groups)
+            .enumerate()
         {
-            let x: Vec<_> = input_channel.into_iter()
-                .map(|&x| x.to_f64().unwrap())
+            if lfe == Some(i)
+            {
+                // The LFE channel bypasses the filter and only takes the output gain, and has nothing to reject
+                for ((y, &x), &output_gain) in y[block.clone()].iter_mut()
+                    .zip(x)
+                    .zip(&output_gain)
+                {
+                    *y = x*output_gain
+                }
+                continue
+            }
+
+            // The input gain also sets how hard the filtered signal drives the tube stage
+            let makeup = channel.makeup(makeup_mode, self.rate);
+            channel.tube.set_character(tube_character, change);
+            let SpecfilterChannel {filter, tube, allpass, makeup_gain, ..} = channel;
+            let x: Vec<f64> = x.iter()
+                .zip(&input_gain)
+                .map(|(&x, &g)| x*g)
                 .collect();
 
             let mut z = filter.filter_mut(x.as_slice());
@@ -358,36 +645,113 @@ impl SpecfilterPlugin
                 filter.w.clear();
                 z.fill(0.0);
                 
-                if let Some(prev_data) = prev_data
+                if let Some(valid_data) = self.param_valid[group as usize]
                 {
-                    self.param.reset_to(prev_data);
+                    self.param.reset_spec(group, valid_data);
                     if let Some(dispatcher) = self.host.raw_callback()
                     {
                         // Calls dispatch in the same way that setParameterAutomated does
                         // see:
                         // opcode audioMasterAutomate is given in https://github.com/R-Tur/VST_SDK_2.4/blob/master/pluginterfaces/vst2.x/aeffect.h
                         // dispatch is given in setParameterAutomated in https://github.com/R-Tur/VST_SDK_2.4/blob/master/public.sdk/source/vst2.x/audioeffect.cpp
-                        for param_id in [SpecfilterParam::Frequency1, SpecfilterParam::Frequency2, SpecfilterParam::Bandwidth1, SpecfilterParam::Bandwidth2]
+                        for param_id in group.band_params()
                         {
                             dispatcher(self.host.raw_effect(), 0, param_id as i32, 0, std::ptr::null_mut(), self.param.get_parameter(param_id as i32));
                         }
                     }
                 }
-                self.param_prev = None;
+                self.param_prev[group as usize] = None;
             }
 
-            for ((y, z), x) in output_channel.into_iter()
+            for (((((y, y_reject), z), x), &output_gain), &mix) in y[block.clone()].iter_mut()
+                .zip(y_reject[block.clone()].iter_mut())
                 .zip(z)
                 .zip(x)
+                .zip(&output_gain)
+                .zip(&mix)
             {
-                let mut z = tube.next(self.rate, z);
-                if z.is_nan()
+                // Redesigns change the makeup gain, so it glides rather than stepping with every control update
+                let makeup = makeup_gain.next(makeup, change);
+                // The allpass runs all the time, so switching modes does not start it from silence
+                // The complement is taken before makeup and saturation, so that pass and reject sum back to the dry path
+                let (dry_coherent, reject) = allpass.complement(x, z);
+
+                match output_mode
                 {
-                    z = 0.0;
-                    tube.reset();
+                    OutputMode::Normal => {
+                        let mut z = tube.next(self.rate, z*makeup);
+                        if z.is_nan()
+                        {
+                            z = 0.0;
+                            tube.reset();
+                        }
+                        let z = z.max(-10.0).min(10.0);
+                        let dry = if phase_coherent {dry_coherent} else {x};
+                        *y = (z*mix + dry*(1.0 - mix))*output_gain;
+                    },
+                    OutputMode::Delta => *y = reject*output_gain,
+                    OutputMode::Split => {
+                        *y = z*output_gain;
+                        *y_reject = reject*output_gain;
+                    }
                 }
-                let z = z.max(-10.0).min(10.0);
-                *y = T::from(z*mix + x*(1.0 - mix)).unwrap();
+            }
+        }
+
+        if mid_side
+        {
+            // Decodes the processed mid and side back to left and right, along with the rejected content
+            for y in [y_main, y_reject]
+            {
+                let [l, r] = y
+                else
+                {
+                    continue
+                };
+                for (l, r) in l[block.clone()].iter_mut()
+                    .zip(r[block.clone()].iter_mut())
+                {
+                    (*l, *r) = (*l + *r, *l - *r)
+                }
+            }
+        }
+
+        self.bypass(x, y, block.clone());
+        self.mono_analyzer.process(&self.param.mono_meter, y, block, self.rate);
+    }
+
+    /// Crossfades between the processed output and the untouched input as bypass is switched.
+    /// With reset on bypass, the filters start over from silence when coming back, otherwise they keep running in the background.
+    fn bypass(&mut self, x: &[Vec<f64>], y: &mut [Vec<f64>], block: Range<usize>)
+    {
+        let target = if self.param.bypass() {1.0} else {0.0};
+        if self.bypass_fade == 0.0 && target == 0.0
+        {
+            return
+        }
+
+        let step = 1.0/(BYPASS_FADE*self.rate);
+        let (y, y_reject) = y.split_at_mut(self.channels.len());
+        for i in block
+        {
+            self.bypass_fade = if target > self.bypass_fade
+            {
+                (self.bypass_fade + step).min(target)
+            }
+            else
+            {
+                (self.bypass_fade - step).max(target)
+            };
+            let fade = self.bypass_fade;
+
+            for (x, y) in x.iter()
+                .zip(y.iter_mut())
+            {
+                y[i] = y[i]*(1.0 - fade) + x[i]*fade
+            }
+            for y in y_reject.iter_mut()
+            {
+                y[i] *= 1.0 - fade
             }
         }
     }
@@ -401,10 +765,26 @@ impl Plugin for SpecfilterPlugin
     {        
         SpecfilterPlugin {
             param: Arc::new(SpecfilterParameters::default()),
-            param_prev: None,
-            filter_type: FilterType::AllPass,
-            filter: core::array::from_fn(|_| Rtf::new(Sos::one(), ())),
-            tubes: core::array::from_fn(|_| TubeStage::new()),
+            param_prev: [None; SpecGroup::VARIANT_COUNT],
+            param_valid: [None; SpecGroup::VARIANT_COUNT],
+            channels: (0..ChannelLayout::Stereo.channel_count())
+                .map(|_| SpecfilterChannel::new())
+                .collect(),
+            layout: ChannelLayout::Stereo,
+            key_tracker: KeyTracker::new(),
+            lfo: Lfo::new(),
+            envelope: EnvelopeFollower::new(),
+            envelope_level: 0.0,
+            sidechain: SidechainDetector::new(),
+            sidechain_level: 0.0,
+            pitch_tracker: PitchTracker::new(44100.0),
+            mono_analyzer: MonoAnalyzer::new(),
+            gains: [Smoothed::new(); 3],
+            events: vec![],
+            position: 0,
+            last_update: 0,
+            key_changed: false,
+            bypass_fade: 0.0,
             rate: 44100.0,
             host
         }
@@ -415,26 +795,61 @@ impl Plugin for SpecfilterPlugin
         Info {
             name: "Specfilter".to_string(),
             vendor: "Soma FX".to_string(),
-            presets: 0,
+            presets: FACTORY_PRESET_COUNT as i32,
             parameters: SpecfilterParam::VARIANT_COUNT as i32,
-            inputs: CHANNEL_COUNT as i32,
-            outputs: CHANNEL_COUNT as i32,
-            midi_inputs: 0,
+            inputs: io_count(self.layout).0,
+            outputs: io_count(self.layout).1,
+            midi_inputs: 1,
             midi_outputs: 0,
             unique_id: 235925,
             version: 1,
             category: Category::Effect,
             initial_delay: 0,
-            preset_chunks: false,
+            preset_chunks: true,
             f64_precision: true,
-            silent_when_stopped: true,
             ..Default::default()
         }
     }
 
     fn set_sample_rate(&mut self, rate: f32)
     {
+        if rate as f64 == self.rate && self.param.rate.get() == rate
+        {
+            return
+        }
         self.rate = rate as f64;
+
+        let changed = self.param.set_rate(rate);
+
+        for channel in self.channels.iter_mut()
+        {
+            channel.reset()
+        }
+        self.envelope.reset();
+        self.sidechain.reset();
+        self.pitch_tracker.set_rate(self.rate);
+        self.mono_analyzer.reset(&self.param.mono_meter);
+        self.envelope_level = 0.0;
+        self.sidechain_level = 0.0;
+        self.param_prev = [None; SpecGroup::VARIANT_COUNT];
+        self.param_valid = [None; SpecGroup::VARIANT_COUNT];
+        self.position = 0;
+        self.last_update = 0;
+        self.key_tracker.reset();
+        self.lfo.reset();
+
+        // Redesigns right away, so the first block at the new rate does not run the old coefficients
+        self.control_update();
+
+        for param_id in changed
+        {
+            self.host.automate(param_id as i32, self.param.get_parameter(param_id as i32));
+        }
+    }
+
+    fn resume(&mut self)
+    {
+        self.update_layout()
     }
 
     fn process(&mut self, buffer: &mut AudioBuffer<f32>)
@@ -447,6 +862,76 @@ impl Plugin for SpecfilterPlugin
         self.process(buffer)
     }
 
+    fn get_output_info(&self, output: i32) -> ChannelInfo
+    {
+        let output = output as usize;
+        let channels = self.channels.len();
+        if output < channels
+        {
+            ChannelInfo::new(format!("Output {}", self.layout.channel_name(output)), None, true, None)
+        }
+        else
+        {
+            ChannelInfo::new(format!("Reject {}", self.layout.channel_name(output - channels)), None, true, None)
+        }
+    }
+
+    fn get_input_info(&self, input: i32) -> ChannelInfo
+    {
+        let input = input as usize;
+        let channels = self.channels.len();
+        if input < channels
+        {
+            ChannelInfo::new(format!("Input {}", self.layout.channel_name(input)), None, true, None)
+        }
+        else
+        {
+            ChannelInfo::new(format!("Sidechain {}", input - channels + 1), None, true, None)
+        }
+    }
+
+    fn get_tail_size(&self) -> isize
+    {
+        self.channels.iter()
+            .map(|channel| channel.tail(self.rate))
+            .max()
+            .unwrap_or(0)
+            // A tail size of 0 means the host's default, while 1 means no tail
+            .max(1) as isize
+    }
+
+    fn can_do(&self, can_do: CanDo) -> Supported
+    {
+        match can_do
+        {
+            CanDo::ReceiveEvents | CanDo::ReceiveMidiEvent | CanDo::Bypass => Supported::Yes,
+            _ => Supported::Maybe
+        }
+    }
+
+    fn process_events(&mut self, events: &Events)
+    {
+        for event in events.events()
+        {
+            if let Event::Midi(event) = event
+            {
+                self.events.push((event.delta_frames.max(0) as usize, event.data))
+            }
+        }
+    }
+
+    fn init(&mut self)
+    {
+        let effect = self.host.raw_effect();
+        if !effect.is_null()
+        {
+            unsafe {
+                let _ = DISPATCHER.set((*effect).dispatcher);
+                (*effect).dispatcher = dispatch;
+            }
+        }
+    }
+
     fn get_parameter_object(&mut self) -> Arc<dyn PluginParameters>
     {
         self.param.clone()
@@ -0,0 +1,33 @@
+use core::fmt::Display;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum LinkMode
+{
+    Unlinked,
+    Absolute,
+    Relative
+}
+
+impl LinkMode
+{
+    pub const VARIANT_COUNT: usize = core::mem::variant_count::<Self>();
+    pub const VARIANTS: [Self; Self::VARIANT_COUNT] = [
+        Self::Unlinked,
+        Self::Absolute,
+        Self::Relative
+    ];
+    pub const VARIANT_NAMES: [&'static str; Self::VARIANT_COUNT] = [
+        "Unlinked",
+        "Absolute",
+        "Relative"
+    ];
+}
+
+impl Display for LinkMode
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", Self::VARIANT_NAMES[*self as usize])
+    }
+}
\ No newline at end of file
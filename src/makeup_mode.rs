@@ -0,0 +1,36 @@
+use core::fmt::Display;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MakeupMode
+{
+    Off,
+    Peak,
+    PassbandAverage,
+    Center
+}
+
+impl MakeupMode
+{
+    pub const VARIANT_COUNT: usize = core::mem::variant_count::<Self>();
+    pub const VARIANTS: [Self; Self::VARIANT_COUNT] = [
+        Self::Off,
+        Self::Peak,
+        Self::PassbandAverage,
+        Self::Center
+    ];
+    pub const VARIANT_NAMES: [&'static str; Self::VARIANT_COUNT] = [
+        "Off",
+        "Peak",
+        "Passband average",
+        "Center"
+    ];
+}
+
+impl Display for MakeupMode
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", Self::VARIANT_NAMES[*self as usize])
+    }
+}
\ No newline at end of file
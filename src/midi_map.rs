@@ -0,0 +1,121 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use vst::util::AtomicFloat;
+
+use crate::preset;
+
+const CURVE_OCTAVES: f32 = 3.0;
+const NO_CC: u8 = u8::MAX;
+
+/// A MIDI CC assignment of a parameter.
+/// The range and curve are kept even when no controller is assigned, so that they can be set up before learning.
+#[derive(Clone, Copy, PartialEq)]
+pub struct MidiMapping
+{
+    pub cc: Option<u8>,
+    pub min: f32,
+    pub max: f32,
+    pub curve: f32
+}
+
+impl Default for MidiMapping
+{
+    fn default() -> Self
+    {
+        Self {
+            cc: None,
+            min: 0.0,
+            max: 1.0,
+            curve: 0.0
+        }
+    }
+}
+
+impl MidiMapping
+{
+    /// Maps a controller value in the range 0.0 to 1.0 to a normalized parameter value.
+    pub fn map(&self, value: f32) -> f32
+    {
+        let value = value.max(0.0).min(1.0).powf((self.curve*CURVE_OCTAVES).exp2());
+        self.min + (self.max - self.min)*value
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        [
+            [self.cc.unwrap_or(NO_CC)].as_slice(),
+            self.min.to_le_bytes().as_slice(),
+            self.max.to_le_bytes().as_slice(),
+            self.curve.to_le_bytes().as_slice()
+        ].concat()
+    }
+
+    /// Reads a mapping from the front of `data`, advancing it past the bytes that were consumed.
+    pub fn from_bytes(data: &mut &[u8]) -> Option<Self>
+    {
+        let (&cc, rest) = data.split_first()?;
+        *data = rest;
+        Some(Self {
+            cc: if cc == NO_CC {None} else {Some(cc)},
+            min: preset::read_f32(data)?,
+            max: preset::read_f32(data)?,
+            curve: preset::read_f32(data)?
+        })
+    }
+}
+
+/// A MIDI CC assignment shared between the audio and host threads.
+/// Each field is atomic, so that applying controllers on the audio thread never has to wait for the host.
+pub struct AtomicMidiMapping
+{
+    cc: AtomicU8,
+    pub min: AtomicFloat,
+    pub max: AtomicFloat,
+    pub curve: AtomicFloat
+}
+
+impl Default for AtomicMidiMapping
+{
+    fn default() -> Self
+    {
+        let mapping = MidiMapping::default();
+        Self {
+            cc: AtomicU8::new(mapping.cc.unwrap_or(NO_CC)),
+            min: AtomicFloat::new(mapping.min),
+            max: AtomicFloat::new(mapping.max),
+            curve: AtomicFloat::new(mapping.curve)
+        }
+    }
+}
+
+impl AtomicMidiMapping
+{
+    pub fn cc(&self) -> Option<u8>
+    {
+        Some(self.cc.load(Ordering::Relaxed))
+            .filter(|&cc| cc != NO_CC)
+    }
+
+    pub fn set_cc(&self, cc: Option<u8>)
+    {
+        self.cc.store(cc.unwrap_or(NO_CC), Ordering::Relaxed)
+    }
+
+    pub fn get(&self) -> MidiMapping
+    {
+        MidiMapping {
+            cc: self.cc(),
+            min: self.min.get(),
+            max: self.max.get(),
+            curve: self.curve.get()
+        }
+    }
+
+    pub fn set(&self, mapping: MidiMapping)
+    {
+        self.set_cc(mapping.cc);
+        self.min.set(mapping.min);
+        self.max.set(mapping.max);
+        self.curve.set(mapping.curve);
+    }
+}
\ No newline at end of file
@@ -1,6 +1,8 @@
+use core::f32::consts::LN_2;
 use core::f32::EPSILON;
 use core::sync::atomic::AtomicBool;
-use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
 
 use num_traits::float::TotalOrder;
 use num_traits::{Float, Zero};
@@ -9,13 +11,67 @@ use vst::util::AtomicFloat;
 
 use crate::filter_type::FilterType;
 use crate::filter_kind::FilterKind;
+use crate::key_target::KeyTarget;
+use crate::midi_map::{AtomicMidiMapping, MidiMapping};
+use crate::lfo::{LFO_DIVISIONS, LFO_DIVISION_COUNT};
+use crate::lfo_shape::LfoShape;
+use crate::envelope_detector::EnvelopeDetector;
+use crate::sidechain_mode::SidechainMode;
+use crate::pitch_target::PitchTarget;
+use crate::bandwidth_unit::BandwidthUnit;
+use crate::response_type::ResponseType;
+use crate::spec_mode::SpecMode;
+use crate::makeup_mode::MakeupMode;
+use crate::output_mode::OutputMode;
+use crate::channel_layout::ChannelLayout;
+use crate::stereo_mode::StereoMode;
+use crate::spec_group::SpecGroup;
+use crate::link_mode::LinkMode;
+use crate::analysis::MonoMeter;
+use crate::tube_preset::TubePreset;
+use crate::tube_stage::TubeCharacter;
+use crate::preset::{self, SpecfilterPreset, FACTORY_PRESETS};
+use crate::units::{self, Width};
 
 const MIN_FREQ: f32 = 1.0;
 const MAX_FREQ: f32 = 30000.0;
 const MIN_TRANSITION_BAND: f32 = 0.01;
+/// Narrowest transition band in octaves for directly given edges.
+const MIN_TRANSITION_OCTAVES: f32 = 0.01;
 const MIN_RIPPLE: f32 = 1.0;
 const MAX_RIPPLE: f32 = 100.0;
 const BW_EPS: f32 = 0.00001;
+const BW_SEARCH_ITERATIONS: usize = 32;
+const MAX_PITCH_BEND_RANGE: f32 = 24.0;
+const MIN_LFO_RATE: f32 = 0.01;
+const MAX_LFO_RATE: f32 = 20.0;
+const MAX_LFO_DEPTH: f32 = 4.0;
+const MIN_ATTACK: f32 = 0.1;
+const MAX_ATTACK: f32 = 1000.0;
+const MIN_RELEASE: f32 = 1.0;
+const MAX_RELEASE: f32 = 5000.0;
+const MAX_SENSITIVITY: f32 = 48.0;
+const MAX_ENVELOPE_DEPTH: f32 = 4.0;
+const MIN_SIDECHAIN_Q: f32 = 0.1;
+const MAX_SIDECHAIN_Q: f32 = 20.0;
+const MIN_PITCH_RATIO: f32 = 0.25;
+const MAX_PITCH_RATIO: f32 = 16.0;
+const MIN_GLIDE: f32 = 1.0;
+const MAX_GLIDE: f32 = 5000.0;
+const MIN_SMOOTHING: f32 = 0.1;
+const MAX_SMOOTHING: f32 = 1000.0;
+const MIN_WIDTH: f32 = 0.01;
+const MAX_WIDTH: f32 = 10.0;
+const MAX_GAIN: f32 = 24.0;
+const MAX_SPREAD: f32 = 2.0;
+const MAX_TUBE_BIAS: f32 = 1.0;
+const MAX_TUBE_SHAPE: f32 = 2.0;
+const MIN_TUBE_LOW_CORNER: f32 = 5.0;
+const MAX_TUBE_LOW_CORNER: f32 = 200.0;
+const MIN_TUBE_HIGH_CORNER: f32 = 1000.0;
+const MAX_TUBE_HIGH_CORNER: f32 = 20000.0;
+/// Marks the spec mode parameters, which only shape spec group A. Group B is always given by its own frequencies and bandwidths.
+const A_ONLY: &str = " (A only)";
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum SpecfilterParam
@@ -28,6 +84,73 @@ pub enum SpecfilterParam
     Frequency2,
     Bandwidth1,
     Bandwidth2,
+    KeyTarget,
+    KeyTracking,
+    PitchBendRange,
+    VelocityBandwidth,
+    MidiLearn,
+    MidiMapParameter,
+    MidiMapMin,
+    MidiMapMax,
+    MidiMapCurve,
+    LfoShape,
+    LfoSync,
+    LfoRate,
+    LfoDivision,
+    LfoDepth,
+    LfoStereoPhase,
+    EnvelopeDetector,
+    EnvelopeAttack,
+    EnvelopeRelease,
+    EnvelopeSensitivity,
+    EnvelopeDepth,
+    EnvelopeBandwidth,
+    SidechainMode,
+    SidechainFrequency,
+    SidechainQ,
+    SidechainSensitivity,
+    SidechainDepth,
+    PitchTarget,
+    PitchRatio,
+    PitchConfidence,
+    PitchGlide,
+    Smoothing,
+    BandwidthUnit,
+    ResponseType,
+    SpecMode,
+    Center,
+    Width,
+    PassbandEdge1,
+    StopbandEdge1,
+    PassbandEdge2,
+    StopbandEdge2,
+    InputGain,
+    OutputGain,
+    MakeupMode,
+    PhaseCoherentMix,
+    OutputMode,
+    Bypass,
+    BypassReset,
+    ChannelLayout,
+    LfeFilter,
+    StereoMode,
+    FilterKindB,
+    PassbandRippleB,
+    StopbandAttenuationB,
+    Frequency1B,
+    Frequency2B,
+    Bandwidth1B,
+    Bandwidth2B,
+    ResponseTypeB,
+    LinkMode,
+    Spread,
+    TubeDrive,
+    TubeBias,
+    TubeOvertones,
+    TubeSoftness,
+    TubeLowCorner,
+    TubeHighCorner,
+    TubePreset,
 }
 
 impl SpecfilterParam
@@ -42,6 +165,117 @@ impl SpecfilterParam
         Self::Frequency2,
         Self::Bandwidth1,
         Self::Bandwidth2,
+        Self::KeyTarget,
+        Self::KeyTracking,
+        Self::PitchBendRange,
+        Self::VelocityBandwidth,
+        Self::MidiLearn,
+        Self::MidiMapParameter,
+        Self::MidiMapMin,
+        Self::MidiMapMax,
+        Self::MidiMapCurve,
+        Self::LfoShape,
+        Self::LfoSync,
+        Self::LfoRate,
+        Self::LfoDivision,
+        Self::LfoDepth,
+        Self::LfoStereoPhase,
+        Self::EnvelopeDetector,
+        Self::EnvelopeAttack,
+        Self::EnvelopeRelease,
+        Self::EnvelopeSensitivity,
+        Self::EnvelopeDepth,
+        Self::EnvelopeBandwidth,
+        Self::SidechainMode,
+        Self::SidechainFrequency,
+        Self::SidechainQ,
+        Self::SidechainSensitivity,
+        Self::SidechainDepth,
+        Self::PitchTarget,
+        Self::PitchRatio,
+        Self::PitchConfidence,
+        Self::PitchGlide,
+        Self::Smoothing,
+        Self::BandwidthUnit,
+        Self::ResponseType,
+        Self::SpecMode,
+        Self::Center,
+        Self::Width,
+        Self::PassbandEdge1,
+        Self::StopbandEdge1,
+        Self::PassbandEdge2,
+        Self::StopbandEdge2,
+        Self::InputGain,
+        Self::OutputGain,
+        Self::MakeupMode,
+        Self::PhaseCoherentMix,
+        Self::OutputMode,
+        Self::Bypass,
+        Self::BypassReset,
+        Self::ChannelLayout,
+        Self::LfeFilter,
+        Self::StereoMode,
+        Self::FilterKindB,
+        Self::PassbandRippleB,
+        Self::StopbandAttenuationB,
+        Self::Frequency1B,
+        Self::Frequency2B,
+        Self::Bandwidth1B,
+        Self::Bandwidth2B,
+        Self::ResponseTypeB,
+        Self::LinkMode,
+        Self::Spread,
+        Self::TubeDrive,
+        Self::TubeBias,
+        Self::TubeOvertones,
+        Self::TubeSoftness,
+        Self::TubeLowCorner,
+        Self::TubeHighCorner,
+        Self::TubePreset,
+    ];
+
+    /// Global parameters belong to the plugin instance rather than to a preset, so loading a preset leaves them alone.
+    pub fn is_global(&self) -> bool
+    {
+        matches!(self, Self::MidiLearn | Self::MidiMapParameter | Self::MidiMapMin | Self::MidiMapMax | Self::MidiMapCurve | Self::ChannelLayout | Self::LfeFilter)
+    }
+
+    /// The parameters that can be assigned a MIDI controller. Global parameters are left out, as they set up the plugin rather than the sound.
+    pub fn mappable() -> impl Iterator<Item = Self>
+    {
+        Self::VARIANTS.into_iter()
+            .filter(|v| !v.is_global())
+    }
+
+    /// The matching parameter in the other spec group, which follows this one when the groups are linked.
+    pub fn counterpart(&self) -> Option<Self>
+    {
+        match self
+        {
+            Self::FilterKind => Some(Self::FilterKindB),
+            Self::PassbandRipple => Some(Self::PassbandRippleB),
+            Self::StopbandAttenuation => Some(Self::StopbandAttenuationB),
+            Self::Frequency1 => Some(Self::Frequency1B),
+            Self::Frequency2 => Some(Self::Frequency2B),
+            Self::Bandwidth1 => Some(Self::Bandwidth1B),
+            Self::Bandwidth2 => Some(Self::Bandwidth2B),
+            Self::ResponseType => Some(Self::ResponseTypeB),
+            Self::FilterKindB => Some(Self::FilterKind),
+            Self::PassbandRippleB => Some(Self::PassbandRipple),
+            Self::StopbandAttenuationB => Some(Self::StopbandAttenuation),
+            Self::Frequency1B => Some(Self::Frequency1),
+            Self::Frequency2B => Some(Self::Frequency2),
+            Self::Bandwidth1B => Some(Self::Bandwidth1),
+            Self::Bandwidth2B => Some(Self::Bandwidth2),
+            Self::ResponseTypeB => Some(Self::ResponseType),
+            _ => None
+        }
+    }
+
+    /// Global parameters that are saved with the bank, after the MIDI map.
+    pub const LAYOUT: [Self; 2] = [
+        Self::ChannelLayout,
+        Self::LfeFilter
     ];
 }
 
@@ -53,7 +287,71 @@ pub struct SpecfilterParameters
     pub frequencies: [AtomicFloat; 2],
     pub bandwidths: [AtomicFloat; 2],
     pub mix: AtomicFloat,
-    pub rate: AtomicFloat
+    pub key_target: AtomicU8,
+    pub key_tracking: AtomicFloat,
+    pub pitch_bend_range: AtomicFloat,
+    pub velocity_bandwidth: AtomicFloat,
+    pub midi_learn: AtomicBool,
+    pub midi_map_parameter: AtomicU8,
+    pub midi_map: [AtomicMidiMapping; SpecfilterParam::VARIANT_COUNT],
+    /// Parameters that moved along with a linked one set by the host, which the host still has to be told about.
+    linked_changed: [AtomicBool; SpecfilterParam::VARIANT_COUNT],
+    pub lfo_shape: AtomicU8,
+    pub lfo_sync: AtomicBool,
+    pub lfo_rate: AtomicFloat,
+    pub lfo_division: AtomicU8,
+    pub lfo_depth: AtomicFloat,
+    pub lfo_stereo_phase: AtomicFloat,
+    pub envelope_detector: AtomicU8,
+    pub envelope_attack: AtomicFloat,
+    pub envelope_release: AtomicFloat,
+    pub envelope_sensitivity: AtomicFloat,
+    pub envelope_depth: AtomicFloat,
+    pub envelope_bandwidth: AtomicFloat,
+    pub sidechain_mode: AtomicU8,
+    pub sidechain_frequency: AtomicFloat,
+    pub sidechain_q: AtomicFloat,
+    pub sidechain_sensitivity: AtomicFloat,
+    pub sidechain_depth: AtomicFloat,
+    pub pitch_target: AtomicU8,
+    pub pitch_ratio: AtomicFloat,
+    pub pitch_confidence: AtomicFloat,
+    pub pitch_glide: AtomicFloat,
+    pub smoothing: AtomicFloat,
+    pub bandwidth_unit: AtomicU8,
+    pub response_type: AtomicU8,
+    pub spec_mode: AtomicU8,
+    pub center: AtomicFloat,
+    pub width: AtomicFloat,
+    pub edges: [AtomicFloat; 4],
+    pub input_gain: AtomicFloat,
+    pub output_gain: AtomicFloat,
+    pub makeup_mode: AtomicU8,
+    pub phase_coherent_mix: AtomicBool,
+    pub output_mode: AtomicU8,
+    pub bypass: AtomicBool,
+    pub bypass_reset: AtomicBool,
+    pub channel_layout: AtomicU8,
+    pub lfe_filter: AtomicBool,
+    pub stereo_mode: AtomicU8,
+    pub filter_kind_b: AtomicU8,
+    pub passband_ripple_b: AtomicFloat,
+    pub stopband_attenuation_b: AtomicFloat,
+    pub frequencies_b: [AtomicFloat; 2],
+    pub bandwidths_b: [AtomicFloat; 2],
+    pub response_type_b: AtomicU8,
+    pub link_mode: AtomicU8,
+    pub spread: AtomicFloat,
+    pub mono_meter: MonoMeter,
+    pub tube_drive: AtomicFloat,
+    pub tube_bias: AtomicFloat,
+    pub tube_overtones: AtomicFloat,
+    pub tube_softness: AtomicFloat,
+    pub tube_corners: [AtomicFloat; 2],
+    pub tube_preset: AtomicU8,
+    pub rate: AtomicFloat,
+    pub preset: AtomicUsize,
+    pub presets: Mutex<Vec<SpecfilterPreset>>
 }
 
 impl From<&SpecfilterParameters> for SpecfilterParamData
@@ -64,10 +362,17 @@ impl From<&SpecfilterParameters> for SpecfilterParamData
             filter_kind: param.filter_kind(),
             passband_ripple: param.passband_ripple.get(),
             stopband_attenuation: param.stopband_attenuation.get(),
-            frequencies: param.frequencies.each_ref()
-                .map(|f| f.get()),
+            frequencies: param.edge_frequencies(),
             bandwidths: param.bandwidths.each_ref()
-                .map(|f| f.get())
+                .map(|f| f.get()),
+            response_type: param.response_type(),
+            edges: match param.spec_mode()
+            {
+                SpecMode::Direct => Some(param.edges.each_ref()
+                    .map(|f| f.get())
+                ),
+                _ => None
+            }
         }
     }
 }
@@ -79,26 +384,37 @@ pub struct SpecfilterParamData
     pub passband_ripple: f32,
     pub stopband_attenuation: f32,
     pub frequencies: [f32; 2],
-    pub bandwidths: [f32; 2]
+    pub bandwidths: [f32; 2],
+    pub response_type: ResponseType,
+    /// Passband and stopband edges given directly, in the order fp1, fs1, fp2, fs2, which then take the place of the frequencies and bandwidths.
+    pub edges: Option<[f32; 4]>
 }
 
 impl SpecfilterParamData
 {
+    /// Moves towards `new` by the fraction `change`.
+    /// Each parameter is interpolated in its perceptual domain, that is decibels for ripple and attenuation, and octaves for frequencies, so a glide moves equally fast at the low and high end.
     pub fn change(&mut self, new: SpecfilterParamData, change: f32)
     {
         self.filter_kind = new.filter_kind;
+        self.response_type = new.response_type;
         self.passband_ripple = new.passband_ripple*change + self.passband_ripple*(1.0 - change);
         self.stopband_attenuation = new.stopband_attenuation*change + self.stopband_attenuation*(1.0 - change);
         for (f1, f2) in self.frequencies.iter_mut()
             .zip(new.frequencies)
         {
-            *f1 = f2*change + *f1*(1.0 - change)
+            *f1 = (f2.log2()*change + f1.log2()*(1.0 - change)).exp2()
         }
         for (f1, f2) in self.bandwidths.iter_mut()
             .zip(new.bandwidths)
         {
             *f1 = f2*change + *f1*(1.0 - change)
         }
+        self.edges = match (self.edges, new.edges)
+        {
+            (Some(e1), Some(e2)) => Some(core::array::from_fn(|i| (e2[i].log2()*change + e1[i].log2()*(1.0 - change)).exp2())),
+            (_, edges) => edges
+        };
     }
 }
 
@@ -106,6 +422,15 @@ impl SpecfilterParamData
 {
     pub fn frequency_data(&self, rate: f32) -> ([f32; 4], bool, bool, bool)
     {
+        if let Some(edges) = self.edges
+        {
+            return Self::direct_frequency_data(edges, rate)
+        }
+        if self.response_type != ResponseType::Free
+        {
+            return self.free_spec(rate).0.frequency_data(rate)
+        }
+
         let mut freq = self.frequencies;
         let mut bw = self.bandwidths;
 
@@ -185,6 +510,80 @@ impl SpecfilterParamData
         (freq, stop, nolb, noub)
     }
 
+    /// Validates directly given edges, bypassing the bandwidth mapping.
+    /// The order of the lower pair of edges decides between pass and stop, or the upper pair if the lower one sits on the lower bound.
+    /// Each inner edge is clamped against its outer partner to keep the transition band at least `MIN_TRANSITION_OCTAVES` wide, and the inner edges are kept at least `BW_EPS` apart.
+    fn direct_frequency_data(edges: [f32; 4], rate: f32) -> ([f32; 4], bool, bool, bool)
+    {
+        let max_freq = MAX_FREQ.min(rate/2.0);
+        let ratio = MIN_TRANSITION_OCTAVES.exp2();
+
+        let [fp1, fs1, fp2, fs2] = edges.map(|f| f.max(MIN_FREQ).min(max_freq));
+        let nolb = fp1.min(fs1) <= MIN_FREQ + EPSILON;
+        let noub = fp2.max(fs2) >= max_freq - EPSILON;
+        let stop = if nolb {fs2 < fp2} else {fp1 < fs1};
+
+        // In a band-pass the stopband edges are the outer ones, and in a band-stop the passband edges
+        let (outer1, inner1) = if stop {(fp1, fs1)} else {(fs1, fp1)};
+        let (inner2, outer2) = if stop {(fs2, fp2)} else {(fp2, fs2)};
+
+        let (mut a, mut b) = if nolb {(MIN_FREQ, MIN_FREQ)} else {(outer1, inner1.max(outer1*ratio).min(max_freq))};
+        let (mut c, mut d) = if noub {(max_freq, max_freq)} else {(inner2.min(outer2/ratio).max(MIN_FREQ), outer2)};
+        if !nolb && !noub && c < b*(1.0 + BW_EPS)
+        {
+            let m = (b*c).sqrt();
+            let e = (1.0 + BW_EPS).sqrt();
+            b = m/e;
+            c = m*e;
+            a = a.min(b/ratio);
+            d = d.max(c*ratio);
+        }
+
+        ([a.max(MIN_FREQ), b, c, d.min(max_freq)], stop, nolb, noub)
+    }
+
+    /// The equivalent spec in free mode, where the response type follows from the order of the frequencies and whether they touch the bounds.
+    /// Also returns whether the bandwidths had to be swapped, so that the transition bands can be matched with the bandwidth parameters.
+    /// Low-pass and high-pass only use the first frequency as the cutoff, and band-pass and band-stop order the two frequencies automatically.
+    pub fn free_spec(&self, rate: f32) -> (SpecfilterParamData, bool)
+    {
+        let max_freq = MAX_FREQ.min(rate/2.0);
+        let inner = |f: f32| f.max(MIN_FREQ*(1.0 + BW_EPS)).min(max_freq*(1.0 - BW_EPS));
+
+        let [f0, f1] = self.frequencies.map(inner);
+        let [b0, b1] = self.bandwidths;
+        let (frequencies, bandwidths, swapped) = match self.response_type
+        {
+            _ if self.edges.is_some() => return (*self, false),
+            ResponseType::Free => return (*self, false),
+            ResponseType::LowPass => ([MIN_FREQ, f0], [b1, b0], true),
+            ResponseType::HighPass => ([f0, max_freq], [b0, b1], false),
+            ResponseType::BandPass => if f0 < f1
+            {
+                ([f0, f1], [b0, b1], false)
+            }
+            else
+            {
+                ([f1, f0.max(f1*(1.0 + BW_EPS))], [b1, b0], true)
+            },
+            ResponseType::BandStop => if f0 < f1
+            {
+                ([f1, f0], [b1, b0], true)
+            }
+            else
+            {
+                ([f0, f1], [b0, b1], false)
+            }
+        };
+
+        (Self {
+            frequencies,
+            bandwidths,
+            response_type: ResponseType::Free,
+            ..*self
+        }, swapped)
+    }
+
     pub fn frequencies<T>(&self, rate: f32) -> Result<Result<([T; 2], [T; 2]), ([T; 1], [T; 1])>, bool>
     where
         T: Float
@@ -227,6 +626,91 @@ impl SpecfilterParamData
             }))
     }
 
+    /// Moves the frequencies by the given amount of octaves.
+    /// Frequencies sitting on the lower or upper bound are left there, and moved frequencies are kept off the bounds, so the filter type does not change.
+    pub fn shift_octaves(&mut self, octaves: [f32; 2], rate: f32)
+    {
+        let max_freq = MAX_FREQ.min(rate/2.0);
+        let shift = |f: &mut f32, octaves: f32| if *f > MIN_FREQ + EPSILON && *f < max_freq - EPSILON
+        {
+            *f = (*f*octaves.exp2()).max(MIN_FREQ*(1.0 + BW_EPS)).min(max_freq*(1.0 - BW_EPS))
+        };
+
+        for (f, octaves) in self.frequencies.iter_mut()
+            .zip(octaves)
+        {
+            shift(f, octaves)
+        }
+        if let Some(edges) = &mut self.edges
+        {
+            for (f, octaves) in edges.iter_mut()
+                .zip([octaves[0], octaves[0], octaves[1], octaves[1]])
+            {
+                shift(f, octaves)
+            }
+        }
+    }
+
+    /// The geometric mean of the frequencies that are not sitting on the lower or upper bound.
+    pub fn center(&self, rate: f32) -> Option<f32>
+    {
+        let max_freq = MAX_FREQ.min(rate/2.0);
+
+        let edges = match self.edges
+        {
+            Some([fp1, _, fp2, _]) => [fp1, fp2],
+            None => self.free_spec(rate).0.frequencies
+        };
+        let edges: Vec<f32> = edges.into_iter()
+            .filter(|&f| f > MIN_FREQ + EPSILON && f < max_freq - EPSILON)
+            .collect();
+        if edges.is_empty()
+        {
+            return None
+        }
+        Some((edges.iter().map(|f| f.log2()).sum::<f32>()/edges.len() as f32).exp2())
+    }
+
+    /// Scales the magnitude of the bandwidths, keeping the side of the frequency their transition bands lie on.
+    pub fn scale_bandwidths(&mut self, scale: f32)
+    {
+        for bw in self.bandwidths.iter_mut()
+        {
+            *bw = (*bw*scale.max(BW_EPS)).max(-1.0).min(1.0)
+        }
+        if let Some(edges) = &mut self.edges
+        {
+            for [fp, fs] in edges.array_chunks_mut()
+            {
+                *fs = *fp*(*fs/ *fp).powf(scale.max(BW_EPS))
+            }
+        }
+    }
+
+    /// The lower and upper edge of the transition band shaped by each of the two bandwidth parameters.
+    pub fn transition_bands(&self, rate: f32) -> [[f32; 2]; 2]
+    {
+        let (free, swapped) = self.free_spec(rate);
+        let (freq, stop, _, _) = free.frequency_data(rate);
+
+        let bands = if stop
+        {
+            [[freq[2], freq[3]], [freq[0], freq[1]]]
+        }
+        else
+        {
+            [[freq[0], freq[1]], [freq[2], freq[3]]]
+        };
+        if swapped
+        {
+            [bands[1], bands[0]]
+        }
+        else
+        {
+            bands
+        }
+    }
+
     pub fn filter_type(&self, rate: f32) -> FilterType
     {
         let (_, stop, nolb, noub) = self.frequency_data(rate);
@@ -275,6 +759,16 @@ impl SpecfilterParamData
     }
 }
 
+fn log_normalize(value: f32, min: f32, max: f32) -> f32
+{
+    (value.log2() - min.log2())/(max.log2() - min.log2())
+}
+
+fn log_denormalize(value: f32, min: f32, max: f32) -> f32
+{
+    (value*(max.log2() - min.log2()) + min.log2()).exp2()
+}
+
 impl Default for SpecfilterParameters
 {
     fn default() -> Self
@@ -282,15 +776,95 @@ impl Default for SpecfilterParameters
         let rate = 44100.0;
         let max_freq = MAX_FREQ.min(rate/2.0);
 
-        Self {
+        let param = Self {
             filter_kind: AtomicU8::new(FilterKind::Butterworth as u8),
             passband_ripple: AtomicFloat::new(3.0),
             stopband_attenuation: AtomicFloat::new(40.0),
             frequencies: [0.3, 0.7].map(|w| AtomicFloat::new((w*(max_freq.log2() - MIN_FREQ.log2()) + MIN_FREQ.log2()).exp2())),
             bandwidths: [0.5, 0.5].map(|w| AtomicFloat::new(w)),
             mix: AtomicFloat::new(1.0),
-            rate: AtomicFloat::new(rate)
-        }
+            key_target: AtomicU8::new(KeyTarget::Off as u8),
+            key_tracking: AtomicFloat::new(1.0),
+            pitch_bend_range: AtomicFloat::new(2.0),
+            velocity_bandwidth: AtomicFloat::new(0.0),
+            midi_learn: AtomicBool::new(false),
+            midi_map_parameter: AtomicU8::new(SpecfilterParam::Frequency1 as u8),
+            midi_map: core::array::from_fn(|_| AtomicMidiMapping::default()),
+            linked_changed: core::array::from_fn(|_| AtomicBool::new(false)),
+            lfo_shape: AtomicU8::new(LfoShape::Sine as u8),
+            lfo_sync: AtomicBool::new(false),
+            lfo_rate: AtomicFloat::new(1.0),
+            lfo_division: AtomicU8::new(3),
+            lfo_depth: AtomicFloat::new(0.0),
+            lfo_stereo_phase: AtomicFloat::new(0.0),
+            envelope_detector: AtomicU8::new(EnvelopeDetector::Rms as u8),
+            envelope_attack: AtomicFloat::new(10.0),
+            envelope_release: AtomicFloat::new(200.0),
+            envelope_sensitivity: AtomicFloat::new(12.0),
+            envelope_depth: AtomicFloat::new(0.0),
+            envelope_bandwidth: AtomicFloat::new(0.0),
+            sidechain_mode: AtomicU8::new(SidechainMode::Off as u8),
+            sidechain_frequency: AtomicFloat::new(60.0),
+            sidechain_q: AtomicFloat::new(1.0),
+            sidechain_sensitivity: AtomicFloat::new(12.0),
+            sidechain_depth: AtomicFloat::new(0.0),
+            pitch_target: AtomicU8::new(PitchTarget::Off as u8),
+            pitch_ratio: AtomicFloat::new(1.0),
+            pitch_confidence: AtomicFloat::new(0.8),
+            pitch_glide: AtomicFloat::new(50.0),
+            smoothing: AtomicFloat::new(5.0),
+            bandwidth_unit: AtomicU8::new(BandwidthUnit::Hertz as u8),
+            response_type: AtomicU8::new(ResponseType::Free as u8),
+            spec_mode: AtomicU8::new(SpecMode::Edges as u8),
+            center: AtomicFloat::new(1000.0),
+            width: AtomicFloat::new(1.0),
+            edges: [300.0, 200.0, 3000.0, 4500.0].map(AtomicFloat::new),
+            input_gain: AtomicFloat::new(0.0),
+            output_gain: AtomicFloat::new(0.0),
+            makeup_mode: AtomicU8::new(MakeupMode::Off as u8),
+            phase_coherent_mix: AtomicBool::new(false),
+            output_mode: AtomicU8::new(OutputMode::Normal as u8),
+            bypass: AtomicBool::new(false),
+            bypass_reset: AtomicBool::new(false),
+            channel_layout: AtomicU8::new(ChannelLayout::Stereo as u8),
+            lfe_filter: AtomicBool::new(false),
+            stereo_mode: AtomicU8::new(StereoMode::Stereo as u8),
+            filter_kind_b: AtomicU8::new(FilterKind::Butterworth as u8),
+            passband_ripple_b: AtomicFloat::new(3.0),
+            stopband_attenuation_b: AtomicFloat::new(40.0),
+            frequencies_b: [0.3, 0.7].map(|w| AtomicFloat::new((w*(max_freq.log2() - MIN_FREQ.log2()) + MIN_FREQ.log2()).exp2())),
+            bandwidths_b: [0.5, 0.5].map(|w| AtomicFloat::new(w)),
+            response_type_b: AtomicU8::new(ResponseType::Free as u8),
+            link_mode: AtomicU8::new(LinkMode::Unlinked as u8),
+            spread: AtomicFloat::new(0.0),
+            mono_meter: MonoMeter::new(),
+            tube_drive: AtomicFloat::new(0.0),
+            tube_bias: AtomicFloat::new(0.0),
+            tube_overtones: AtomicFloat::new(1.0),
+            tube_softness: AtomicFloat::new(1.0),
+            tube_corners: TubeCharacter::default().corners.map(|f| AtomicFloat::new(f as f32)),
+            tube_preset: AtomicU8::new(TubePreset::Warm as u8),
+            rate: AtomicFloat::new(rate),
+            preset: AtomicUsize::new(0),
+            presets: Mutex::new(vec![])
+        };
+
+        let init = param.values();
+        let presets = FACTORY_PRESETS.map(|(name, data)| {
+                param.load_values(&init);
+                if let Some(data) = data
+                {
+                    param.reset_to(data);
+                }
+                SpecfilterPreset {
+                    name: name.to_string(),
+                    values: param.values()
+                }
+            });
+        param.load_values(&init);
+        *param.presets.lock().unwrap() = presets.to_vec();
+
+        param
     }
 }
 
@@ -311,6 +885,298 @@ impl SpecfilterParameters
         {
             f1.set(f2)
         }
+        self.response_type.store(to.response_type as u8, Ordering::Relaxed);
+        if let Some(edges) = to.edges
+        {
+            for (f1, f2) in self.edges.iter()
+                .zip(edges)
+            {
+                f1.set(f2)
+            }
+        }
+    }
+
+    /// The spec of a group as set on the knobs. Group B is always given as frequencies and bandwidths.
+    pub fn spec(&self, group: SpecGroup) -> SpecfilterParamData
+    {
+        match group
+        {
+            SpecGroup::A => self.into(),
+            SpecGroup::B => SpecfilterParamData {
+                filter_kind: FilterKind::VARIANTS[self.filter_kind_b.load(Ordering::Relaxed) as usize],
+                passband_ripple: self.passband_ripple_b.get(),
+                stopband_attenuation: self.stopband_attenuation_b.get(),
+                frequencies: self.frequencies_b.each_ref()
+                    .map(|f| f.get()),
+                bandwidths: self.bandwidths_b.each_ref()
+                    .map(|f| f.get()),
+                response_type: ResponseType::VARIANTS[self.response_type_b.load(Ordering::Relaxed) as usize],
+                edges: None
+            }
+        }
+    }
+
+    pub fn reset_spec(&self, group: SpecGroup, to: SpecfilterParamData)
+    {
+        match group
+        {
+            SpecGroup::A => self.reset_to(to),
+            SpecGroup::B => {
+                self.filter_kind_b.store(to.filter_kind as u8, Ordering::Relaxed);
+                self.passband_ripple_b.set(to.passband_ripple);
+                self.stopband_attenuation_b.set(to.stopband_attenuation);
+                for (f1, f2) in self.frequencies_b.iter()
+                    .zip(to.frequencies)
+                {
+                    f1.set(f2)
+                }
+                for (f1, f2) in self.bandwidths_b.iter()
+                    .zip(to.bandwidths)
+                {
+                    f1.set(f2)
+                }
+                self.response_type_b.store(to.response_type as u8, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// The frequency behind a parameter whose normalized value depends on the sample rate.
+    fn rate_frequency(&self, param: SpecfilterParam) -> Option<&AtomicFloat>
+    {
+        match param
+        {
+            SpecfilterParam::Frequency1 => Some(&self.frequencies[0]),
+            SpecfilterParam::Frequency2 => Some(&self.frequencies[1]),
+            SpecfilterParam::Center => Some(&self.center),
+            SpecfilterParam::PassbandEdge1 => Some(&self.edges[0]),
+            SpecfilterParam::StopbandEdge1 => Some(&self.edges[1]),
+            SpecfilterParam::PassbandEdge2 => Some(&self.edges[2]),
+            SpecfilterParam::StopbandEdge2 => Some(&self.edges[3]),
+            SpecfilterParam::Frequency1B => Some(&self.frequencies_b[0]),
+            SpecfilterParam::Frequency2B => Some(&self.frequencies_b[1]),
+            _ => None
+        }
+    }
+
+    /// The values to store in a preset, with the frequencies in Hz so that they stay put when the sample rate changes.
+    pub fn values(&self) -> Vec<f32>
+    {
+        SpecfilterParam::VARIANTS.map(|v| match self.rate_frequency(v)
+            {
+                Some(f) => f.get(),
+                None => self.get_parameter(v as i32)
+            }).to_vec()
+    }
+
+    pub fn load_values(&self, values: &[f32])
+    {
+        for (v, &value) in SpecfilterParam::VARIANTS.into_iter()
+            .zip(values)
+            .filter(|(v, _)| !v.is_global())
+        {
+            match self.rate_frequency(v)
+            {
+                Some(f) => f.set(value.min(self.max_freq()).max(MIN_FREQ)),
+                None => self.store_parameter(v, value)
+            }
+        }
+    }
+
+    pub fn envelope_detector(&self) -> EnvelopeDetector
+    {
+        EnvelopeDetector::VARIANTS[self.envelope_detector.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn sidechain_mode(&self) -> SidechainMode
+    {
+        SidechainMode::VARIANTS[self.sidechain_mode.load(Ordering::Relaxed) as usize]
+    }
+
+    /// The fraction to move the smoothed parameters towards their target after the given amount of samples.
+    /// Since the smoothing is exponential with a fixed time constant, many short steps add up to the same as one long step.
+    pub fn smoothing_change(&self, samples: usize, rate: f64) -> f32
+    {
+        1.0 - (-(samples as f64)/(self.smoothing.get() as f64/1000.0*rate)).exp() as f32
+    }
+
+    pub fn pitch_target(&self) -> PitchTarget
+    {
+        PitchTarget::VARIANTS[self.pitch_target.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn lfo_shape(&self) -> LfoShape
+    {
+        LfoShape::VARIANTS[self.lfo_shape.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn lfo_sync(&self) -> bool
+    {
+        self.lfo_sync.load(Ordering::Relaxed)
+    }
+
+    pub fn phase_coherent_mix(&self) -> bool
+    {
+        self.phase_coherent_mix.load(Ordering::Relaxed)
+    }
+
+    pub fn bypass(&self) -> bool
+    {
+        self.bypass.load(Ordering::Relaxed)
+    }
+
+    pub fn bypass_reset(&self) -> bool
+    {
+        self.bypass_reset.load(Ordering::Relaxed)
+    }
+
+    pub fn stereo_mode(&self) -> StereoMode
+    {
+        StereoMode::VARIANTS[self.stereo_mode.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn link_mode(&self) -> LinkMode
+    {
+        LinkMode::VARIANTS[self.link_mode.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn tube_preset(&self) -> TubePreset
+    {
+        TubePreset::VARIANTS[self.tube_preset.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn tube_character(&self) -> TubeCharacter
+    {
+        TubeCharacter {
+            drive: self.tube_drive.get() as f64,
+            bias: self.tube_bias.get() as f64,
+            overtones: self.tube_overtones.get() as f64,
+            softness: self.tube_softness.get() as f64,
+            corners: self.tube_corners.each_ref()
+                .map(|f| f.get() as f64)
+        }
+    }
+
+    fn set_tube_character(&self, character: TubeCharacter)
+    {
+        self.tube_drive.set(character.drive as f32);
+        self.tube_bias.set(character.bias as f32);
+        self.tube_overtones.set(character.overtones as f32);
+        self.tube_softness.set(character.softness as f32);
+        for (f1, f2) in self.tube_corners.iter()
+            .zip(character.corners)
+        {
+            f1.set(f2 as f32)
+        }
+    }
+
+    pub fn channel_layout(&self) -> ChannelLayout
+    {
+        ChannelLayout::VARIANTS[self.channel_layout.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Whether the LFE channel of a surround layout is filtered along with the others, rather than passed through.
+    pub fn lfe_filter(&self) -> bool
+    {
+        self.lfe_filter.load(Ordering::Relaxed)
+    }
+
+    pub fn lfo_division(&self) -> (&'static str, f64)
+    {
+        LFO_DIVISIONS[self.lfo_division.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn midi_map_parameter(&self) -> SpecfilterParam
+    {
+        SpecfilterParam::VARIANTS[self.midi_map_parameter.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Applies a MIDI CC message. While learning, the controller is first assigned to the selected parameter.
+    /// Returns the parameters that were changed, so that the host can be notified.
+    pub fn midi_cc(&self, cc: u8, value: u8) -> Vec<SpecfilterParam>
+    {
+        let mut changed = vec![];
+
+        if self.midi_learn.swap(false, Ordering::Relaxed)
+        {
+            for mapping in self.midi_map.iter()
+                .filter(|mapping| mapping.cc() == Some(cc))
+            {
+                mapping.set_cc(None)
+            }
+            self.midi_map[self.midi_map_parameter() as usize].set_cc(Some(cc));
+            changed.push(SpecfilterParam::MidiLearn);
+        }
+        for v in SpecfilterParam::mappable()
+        {
+            let mapping = self.midi_map[v as usize].get();
+            if mapping.cc == Some(cc)
+            {
+                changed.push(v);
+                changed.extend(self.apply_parameter(v, mapping.map(value as f32/127.0)));
+            }
+        }
+
+        changed
+    }
+
+    fn midi_map_bytes(&self) -> Vec<u8>
+    {
+        (self.midi_map.len() as u32).to_le_bytes()
+            .into_iter()
+            .chain(self.midi_map.iter()
+                .flat_map(|mapping| mapping.get().to_bytes())
+            ).collect()
+    }
+
+    fn load_midi_map_bytes(&self, data: &mut &[u8])
+    {
+        let Some(count) = preset::read_u32(data)
+        else
+        {
+            return
+        };
+        for i in 0..count as usize
+        {
+            let Some(loaded) = MidiMapping::from_bytes(data)
+            else
+            {
+                return
+            };
+            if let Some(mapping) = self.midi_map.get(i)
+                .filter(|_| !SpecfilterParam::VARIANTS[i].is_global())
+            {
+                mapping.set(loaded)
+            }
+        }
+    }
+
+    fn layout_bytes(&self) -> Vec<u8>
+    {
+        SpecfilterParam::LAYOUT.into_iter()
+            .flat_map(|param| self.get_parameter(param as i32).to_le_bytes())
+            .collect()
+    }
+
+    fn load_layout_bytes(&self, data: &mut &[u8])
+    {
+        for param in SpecfilterParam::LAYOUT
+        {
+            let Some(value) = preset::read_f32(data)
+            else
+            {
+                return
+            };
+            self.set_parameter(param as i32, value);
+        }
+    }
+
+    /// Writes the current parameter values back into the active preset slot, so that edits are kept when switching presets.
+    fn store_preset(&self, presets: &mut [SpecfilterPreset])
+    {
+        if let Some(preset) = presets.get_mut(self.preset.load(Ordering::Relaxed))
+        {
+            preset.values = self.values()
+        }
     }
 
     pub fn filter_kind(&self) -> FilterKind
@@ -318,31 +1184,323 @@ impl SpecfilterParameters
         FilterKind::VARIANTS[self.filter_kind.load(Ordering::Relaxed) as usize]
     }
 
+    pub fn key_target(&self) -> KeyTarget
+    {
+        KeyTarget::VARIANTS[self.key_target.load(Ordering::Relaxed) as usize]
+    }
+
     pub fn frequency_data(&self) -> ([f32; 4], bool, bool, bool)
     {
         SpecfilterParamData::from(self)
             .frequency_data(self.rate.get())
     }
 
-    pub fn bandwidths(&self) -> [f32; 2]
+    pub fn spec_mode(&self) -> SpecMode
     {
-        let (freq, stop, _, _) = self.frequency_data();
-        
-        if stop
+        SpecMode::VARIANTS[self.spec_mode.load(Ordering::Relaxed) as usize]
+    }
+
+    /// The two edge frequencies, either as set directly or as given by the center and width.
+    /// With center and width the edges keep the order of the frequency parameters, which in free mode tells band-pass from band-stop.
+    pub fn edge_frequencies(&self) -> [f32; 2]
+    {
+        let frequencies = self.frequencies.each_ref()
+            .map(|f| f.get());
+        match self.spec_mode()
         {
-            [freq[3] - freq[2], freq[1] - freq[0]]
+            SpecMode::Edges => frequencies,
+            SpecMode::CenterWidth => {
+                let center = self.center.get();
+                let half = self.width.get()*0.5;
+                let edges = [center*(-half).exp2(), center*half.exp2()];
+                if frequencies[0] >= frequencies[1]
+                {
+                    [edges[1], edges[0]]
+                }
+                else
+                {
+                    edges
+                }
+            }
         }
-        else
+    }
+
+    /// Converts a width to octaves around the current center frequency.
+    fn width_octaves(&self, width: Width) -> Option<f32>
+    {
+        let hertz = |w: f32| 2.0*(0.5*w/self.center.get()).asinh()/LN_2;
+        match width
         {
-            [freq[1] - freq[0], freq[3] - freq[2]]
+            Width::Hertz(w) => Some(hertz(w.abs())),
+            Width::Octaves(o) => Some(o.abs()),
+            Width::Percent(p) => Some(hertz(p.abs()/100.0*self.center.get())),
+            Width::Q(q) => Some(Width::q_to_octaves(q.abs())),
+            Width::Erb(_) => None
         }
     }
 
-    pub fn filter_type(&self) -> FilterType
+    pub fn output_mode(&self) -> OutputMode
     {
-        SpecfilterParamData::from(self)
+        OutputMode::VARIANTS[self.output_mode.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn makeup_mode(&self) -> MakeupMode
+    {
+        MakeupMode::VARIANTS[self.makeup_mode.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn response_type(&self) -> ResponseType
+    {
+        ResponseType::VARIANTS[self.response_type.load(Ordering::Relaxed) as usize]
+    }
+
+    pub fn bandwidth_unit(&self) -> BandwidthUnit
+    {
+        BandwidthUnit::VARIANTS[self.bandwidth_unit.load(Ordering::Relaxed) as usize]
+    }
+
+    /// The widths of the transition bands of a group, in the chosen bandwidth unit.
+    pub fn bandwidths(&self, group: SpecGroup) -> [Width; 2]
+    {
+        let unit = self.bandwidth_unit();
+        self.spec(group)
+            .transition_bands(self.rate.get())
+            .map(|[f0, f1]| Width::of_band(unit, f0, f1))
+    }
+
+    pub fn max_freq(&self) -> f32
+    {
+        MAX_FREQ.min(self.rate.get()/2.0)
+    }
+
+    /// Changes the sample rate, keeping the frequencies in Hz and clamping them to the new Nyquist limit.
+    /// Frequencies sitting on the upper bound are moved along with it, so the response type does not change.
+    /// Returns the parameters whose normalized values changed, for the host to be notified of.
+    pub fn set_rate(&self, rate: f32) -> Vec<SpecfilterParam>
+    {
+        let values = SpecfilterParam::VARIANTS.map(|v| self.get_parameter(v as i32));
+        let old_max_freq = self.max_freq();
+
+        self.rate.set(rate);
+
+        let max_freq = self.max_freq();
+        for f in self.frequencies.iter()
+            .chain(self.frequencies_b.iter())
+            .chain(self.edges.iter())
+            .chain([&self.center])
+        {
+            if f.get() >= old_max_freq - EPSILON
+            {
+                f.set(max_freq)
+            }
+            else
+            {
+                f.set(f.get().min(max_freq))
+            }
+        }
+
+        SpecfilterParam::VARIANTS.into_iter()
+            .zip(values)
+            .filter(|&(param, value)| self.get_parameter(param as i32) != value)
+            .map(|(param, _)| param)
+            .collect()
+    }
+
+    fn normalize_frequency(&self, f: f32) -> f32
+    {
+        (f.log2() - MIN_FREQ.log2())/(self.max_freq().log2() - MIN_FREQ.log2())
+    }
+
+    /// Finds the normalized bandwidth parameter value that gives the requested transition band width.
+    /// The sign of the current value is kept, so the transition band stays on the same side of the frequency.
+    fn normalize_bandwidth(&self, group: SpecGroup, i: usize, width: Width) -> f32
+    {
+        let rate = self.rate.get();
+        let mut data = self.spec(group);
+        let sign = if data.bandwidths[i] >= 0.0 {1.0} else {-1.0};
+
+        // Q falls as the band gets wider, so it is searched for in octaves instead
+        let width = match width
+        {
+            Width::Q(q) => Width::Octaves(Width::q_to_octaves(q.abs())),
+            width => width
+        };
+        let target = width.value().abs();
+        let measure = |data: &SpecfilterParamData| {
+            let [f0, f1] = data.transition_bands(rate)[i];
+            Width::of_band(width.unit(), f0, f1).value()
+        };
+
+        let (mut lo, mut hi) = (0.0, 1.0);
+        for _ in 0..BW_SEARCH_ITERATIONS
+        {
+            let mid = (lo + hi)*0.5;
+            data.bandwidths[i] = sign*mid;
+            if measure(&data) < target
+            {
+                lo = mid
+            }
+            else
+            {
+                hi = mid
+            }
+        }
+
+        (sign*(lo + hi)*0.5 + 1.0)*0.5
+    }
+
+    pub fn filter_type(&self, group: SpecGroup) -> FilterType
+    {
+        self.spec(group)
             .filter_type(self.rate.get())
     }
+
+    /// The parameter in the other spec group that follows this one, if the groups are linked in dual mono mode.
+    fn linked(&self, param: SpecfilterParam) -> Option<SpecfilterParam>
+    {
+        if self.stereo_mode() != StereoMode::DualMono || self.link_mode() == LinkMode::Unlinked
+        {
+            return None
+        }
+        param.counterpart()
+    }
+
+    /// Sets a parameter along with its linked counterpart, and the parts of the tube character when choosing one.
+    /// Returns the linked parameter if it was moved, so that the host can be told about it too.
+    pub fn apply_parameter(&self, param: SpecfilterParam, value: f32) -> Option<SpecfilterParam>
+    {
+        let linked = self.linked(param);
+        if let Some(linked) = linked
+        {
+            // Relative linking keeps the offset between the groups, except for the choices, which can only follow along
+            let value = match self.link_mode()
+            {
+                LinkMode::Relative if !matches!(param, SpecfilterParam::FilterKind | SpecfilterParam::FilterKindB | SpecfilterParam::ResponseType | SpecfilterParam::ResponseTypeB) => {
+                    self.get_parameter(linked as i32) + value - self.get_parameter(param as i32)
+                },
+                _ => value
+            };
+            self.store_parameter(linked, value.min(1.0).max(0.0));
+        }
+        self.store_parameter(param, value);
+
+        // Choosing a tube character sets the parts of it, and changing any part of it by hand makes it custom
+        match param
+        {
+            SpecfilterParam::TubePreset => {
+                if let Some(character) = self.tube_preset().character()
+                {
+                    self.set_tube_character(character)
+                }
+            },
+            SpecfilterParam::TubeDrive | SpecfilterParam::TubeBias | SpecfilterParam::TubeOvertones | SpecfilterParam::TubeSoftness
+                | SpecfilterParam::TubeLowCorner | SpecfilterParam::TubeHighCorner => self.tube_preset.store(TubePreset::Custom as u8, Ordering::Relaxed),
+            _ => ()
+        }
+
+        linked
+    }
+
+    /// Takes the parameters that moved along with a linked one since the last call.
+    pub fn take_linked_changes(&self) -> Vec<SpecfilterParam>
+    {
+        SpecfilterParam::VARIANTS.into_iter()
+            .filter(|&v| self.linked_changed[v as usize].swap(false, Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Sets a parameter on its own, without moving any linked parameter along with it.
+    fn store_parameter(&self, param: SpecfilterParam, value: f32)
+    {
+        match param
+        {
+            SpecfilterParam::FilterKind => self.filter_kind.store((value*(FilterKind::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::PassbandRipple => self.passband_ripple.set(value*(MAX_RIPPLE - MIN_RIPPLE) + MIN_RIPPLE),
+            SpecfilterParam::StopbandAttenuation => self.stopband_attenuation.set(value*(MAX_RIPPLE - MIN_RIPPLE) + MIN_RIPPLE),
+            SpecfilterParam::Mix => self.mix.set(value),
+            SpecfilterParam::Frequency1 => {
+                let max_freq = MAX_FREQ.min(self.rate.get()/2.0);
+                self.frequencies[0].set((value*1.000001*(max_freq.log2() - MIN_FREQ.log2()) + MIN_FREQ.log2()).exp2().min(max_freq).max(MIN_FREQ))
+            },
+            SpecfilterParam::Frequency2 => {
+                let max_freq = MAX_FREQ.min(self.rate.get()/2.0);
+                self.frequencies[1].set((value*1.000001*(max_freq.log2() - MIN_FREQ.log2()) + MIN_FREQ.log2()).exp2().min(max_freq).max(MIN_FREQ))
+            },
+            SpecfilterParam::Bandwidth1 => self.bandwidths[0].set(value*2.0 - 1.0),
+            SpecfilterParam::Bandwidth2 => self.bandwidths[1].set(value*2.0 - 1.0),
+            SpecfilterParam::KeyTarget => self.key_target.store((value*(KeyTarget::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::KeyTracking => self.key_tracking.set(value),
+            SpecfilterParam::PitchBendRange => self.pitch_bend_range.set(value*MAX_PITCH_BEND_RANGE),
+            SpecfilterParam::VelocityBandwidth => self.velocity_bandwidth.set(value),
+            SpecfilterParam::MidiLearn => self.midi_learn.store(value >= 0.5, Ordering::Relaxed),
+            SpecfilterParam::MidiMapParameter => if let Some(v) = SpecfilterParam::mappable()
+                .nth((value*(SpecfilterParam::mappable().count() - 1) as f32).round() as usize)
+            {
+                self.midi_map_parameter.store(v as u8, Ordering::Relaxed)
+            },
+            SpecfilterParam::MidiMapMin => self.midi_map[self.midi_map_parameter() as usize].min.set(value),
+            SpecfilterParam::MidiMapMax => self.midi_map[self.midi_map_parameter() as usize].max.set(value),
+            SpecfilterParam::MidiMapCurve => self.midi_map[self.midi_map_parameter() as usize].curve.set(value*2.0 - 1.0),
+            SpecfilterParam::LfoShape => self.lfo_shape.store((value*(LfoShape::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::LfoSync => self.lfo_sync.store(value >= 0.5, Ordering::Relaxed),
+            SpecfilterParam::LfoRate => self.lfo_rate.set((value*(MAX_LFO_RATE.log2() - MIN_LFO_RATE.log2()) + MIN_LFO_RATE.log2()).exp2()),
+            SpecfilterParam::LfoDivision => self.lfo_division.store((value*(LFO_DIVISION_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::LfoDepth => self.lfo_depth.set(value*MAX_LFO_DEPTH),
+            SpecfilterParam::LfoStereoPhase => self.lfo_stereo_phase.set(value),
+            SpecfilterParam::EnvelopeDetector => self.envelope_detector.store((value*(EnvelopeDetector::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::EnvelopeAttack => self.envelope_attack.set(log_denormalize(value, MIN_ATTACK, MAX_ATTACK)),
+            SpecfilterParam::EnvelopeRelease => self.envelope_release.set(log_denormalize(value, MIN_RELEASE, MAX_RELEASE)),
+            SpecfilterParam::EnvelopeSensitivity => self.envelope_sensitivity.set(value*MAX_SENSITIVITY),
+            SpecfilterParam::EnvelopeDepth => self.envelope_depth.set((value*2.0 - 1.0)*MAX_ENVELOPE_DEPTH),
+            SpecfilterParam::EnvelopeBandwidth => self.envelope_bandwidth.set(value*2.0 - 1.0),
+            SpecfilterParam::SidechainMode => self.sidechain_mode.store((value*(SidechainMode::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::SidechainFrequency => self.sidechain_frequency.set(log_denormalize(value, MIN_FREQ, MAX_FREQ)),
+            SpecfilterParam::SidechainQ => self.sidechain_q.set(log_denormalize(value, MIN_SIDECHAIN_Q, MAX_SIDECHAIN_Q)),
+            SpecfilterParam::SidechainSensitivity => self.sidechain_sensitivity.set(value*MAX_SENSITIVITY),
+            SpecfilterParam::SidechainDepth => self.sidechain_depth.set((value*2.0 - 1.0)*MAX_ENVELOPE_DEPTH),
+            SpecfilterParam::PitchTarget => self.pitch_target.store((value*(PitchTarget::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::PitchRatio => self.pitch_ratio.set(log_denormalize(value, MIN_PITCH_RATIO, MAX_PITCH_RATIO)),
+            SpecfilterParam::PitchConfidence => self.pitch_confidence.set(value),
+            SpecfilterParam::PitchGlide => self.pitch_glide.set(log_denormalize(value, MIN_GLIDE, MAX_GLIDE)),
+            SpecfilterParam::Smoothing => self.smoothing.set(log_denormalize(value, MIN_SMOOTHING, MAX_SMOOTHING)),
+            SpecfilterParam::BandwidthUnit => self.bandwidth_unit.store((value*(BandwidthUnit::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::ResponseType => self.response_type.store((value*(ResponseType::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::SpecMode => self.spec_mode.store((value*(SpecMode::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::Center => self.center.set(log_denormalize(value, MIN_FREQ, self.max_freq())),
+            SpecfilterParam::Width => self.width.set(log_denormalize(value, MIN_WIDTH, MAX_WIDTH)),
+            SpecfilterParam::PassbandEdge1 => self.edges[0].set(log_denormalize(value, MIN_FREQ, self.max_freq())),
+            SpecfilterParam::StopbandEdge1 => self.edges[1].set(log_denormalize(value, MIN_FREQ, self.max_freq())),
+            SpecfilterParam::PassbandEdge2 => self.edges[2].set(log_denormalize(value, MIN_FREQ, self.max_freq())),
+            SpecfilterParam::StopbandEdge2 => self.edges[3].set(log_denormalize(value, MIN_FREQ, self.max_freq())),
+            SpecfilterParam::InputGain => self.input_gain.set((value*2.0 - 1.0)*MAX_GAIN),
+            SpecfilterParam::OutputGain => self.output_gain.set((value*2.0 - 1.0)*MAX_GAIN),
+            SpecfilterParam::MakeupMode => self.makeup_mode.store((value*(MakeupMode::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::PhaseCoherentMix => self.phase_coherent_mix.store(value >= 0.5, Ordering::Relaxed),
+            SpecfilterParam::OutputMode => self.output_mode.store((value*(OutputMode::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::Bypass => self.bypass.store(value >= 0.5, Ordering::Relaxed),
+            SpecfilterParam::BypassReset => self.bypass_reset.store(value >= 0.5, Ordering::Relaxed),
+            SpecfilterParam::ChannelLayout => self.channel_layout.store((value*(ChannelLayout::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::LfeFilter => self.lfe_filter.store(value >= 0.5, Ordering::Relaxed),
+            SpecfilterParam::StereoMode => self.stereo_mode.store((value*(StereoMode::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::FilterKindB => self.filter_kind_b.store((value*(FilterKind::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::PassbandRippleB => self.passband_ripple_b.set(value*(MAX_RIPPLE - MIN_RIPPLE) + MIN_RIPPLE),
+            SpecfilterParam::StopbandAttenuationB => self.stopband_attenuation_b.set(value*(MAX_RIPPLE - MIN_RIPPLE) + MIN_RIPPLE),
+            SpecfilterParam::Frequency1B => self.frequencies_b[0].set(log_denormalize(value, MIN_FREQ, self.max_freq()).min(self.max_freq()).max(MIN_FREQ)),
+            SpecfilterParam::Frequency2B => self.frequencies_b[1].set(log_denormalize(value, MIN_FREQ, self.max_freq()).min(self.max_freq()).max(MIN_FREQ)),
+            SpecfilterParam::Bandwidth1B => self.bandwidths_b[0].set(value*2.0 - 1.0),
+            SpecfilterParam::Bandwidth2B => self.bandwidths_b[1].set(value*2.0 - 1.0),
+            SpecfilterParam::ResponseTypeB => self.response_type_b.store((value*(ResponseType::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::LinkMode => self.link_mode.store((value*(LinkMode::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+            SpecfilterParam::Spread => self.spread.set((value*2.0 - 1.0)*MAX_SPREAD),
+            SpecfilterParam::TubeDrive => self.tube_drive.set((value*2.0 - 1.0)*MAX_GAIN),
+            SpecfilterParam::TubeBias => self.tube_bias.set((value*2.0 - 1.0)*MAX_TUBE_BIAS),
+            SpecfilterParam::TubeOvertones => self.tube_overtones.set(value*MAX_TUBE_SHAPE),
+            SpecfilterParam::TubeSoftness => self.tube_softness.set(value*MAX_TUBE_SHAPE),
+            SpecfilterParam::TubeLowCorner => self.tube_corners[0].set(log_denormalize(value, MIN_TUBE_LOW_CORNER, MAX_TUBE_LOW_CORNER)),
+            SpecfilterParam::TubeHighCorner => self.tube_corners[1].set(log_denormalize(value, MIN_TUBE_HIGH_CORNER, MAX_TUBE_HIGH_CORNER)),
+            SpecfilterParam::TubePreset => self.tube_preset.store((value*(TubePreset::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
+        }
+    }
 }
 
 impl PluginParameters for SpecfilterParameters
@@ -351,14 +1509,85 @@ impl PluginParameters for SpecfilterParameters
     {
         match SpecfilterParam::VARIANTS[index as usize]
         {
-            SpecfilterParam::FilterKind => format!("{}", self.filter_type()),
+            SpecfilterParam::FilterKind => format!("{}", self.filter_type(SpecGroup::A)),
             SpecfilterParam::PassbandRipple => "dB".to_string(),
             SpecfilterParam::StopbandAttenuation => "dB".to_string(),
             SpecfilterParam::Mix => "%".to_string(),
-            SpecfilterParam::Frequency1 => "Hz".to_string(),
-            SpecfilterParam::Frequency2 => "Hz".to_string(),
-            SpecfilterParam::Bandwidth1 => "Hz".to_string(),
-            SpecfilterParam::Bandwidth2 => "Hz".to_string(),
+            SpecfilterParam::Frequency1 => "".to_string(),
+            SpecfilterParam::Frequency2 => "".to_string(),
+            SpecfilterParam::Bandwidth1 => self.bandwidths(SpecGroup::A)[0].label().to_string(),
+            SpecfilterParam::Bandwidth2 => self.bandwidths(SpecGroup::A)[1].label().to_string(),
+            SpecfilterParam::KeyTarget => "".to_string(),
+            SpecfilterParam::KeyTracking => "%".to_string(),
+            SpecfilterParam::PitchBendRange => "st".to_string(),
+            SpecfilterParam::VelocityBandwidth => "%".to_string(),
+            SpecfilterParam::MidiLearn => "".to_string(),
+            SpecfilterParam::MidiMapParameter => match self.midi_map[self.midi_map_parameter() as usize].cc()
+            {
+                Some(cc) => format!("CC {}", cc),
+                None => "".to_string()
+            },
+            SpecfilterParam::MidiMapMin => "%".to_string(),
+            SpecfilterParam::MidiMapMax => "%".to_string(),
+            SpecfilterParam::MidiMapCurve => "".to_string(),
+            SpecfilterParam::LfoShape => "".to_string(),
+            SpecfilterParam::LfoSync => "".to_string(),
+            SpecfilterParam::LfoRate => "Hz".to_string(),
+            SpecfilterParam::LfoDivision => "".to_string(),
+            SpecfilterParam::LfoDepth => "oct".to_string(),
+            SpecfilterParam::LfoStereoPhase => "°".to_string(),
+            SpecfilterParam::EnvelopeDetector => "".to_string(),
+            SpecfilterParam::EnvelopeAttack => "ms".to_string(),
+            SpecfilterParam::EnvelopeRelease => "ms".to_string(),
+            SpecfilterParam::EnvelopeSensitivity => "dB".to_string(),
+            SpecfilterParam::EnvelopeDepth => "oct".to_string(),
+            SpecfilterParam::EnvelopeBandwidth => "%".to_string(),
+            SpecfilterParam::SidechainMode => "".to_string(),
+            SpecfilterParam::SidechainFrequency => "".to_string(),
+            SpecfilterParam::SidechainQ => "".to_string(),
+            SpecfilterParam::SidechainSensitivity => "dB".to_string(),
+            SpecfilterParam::SidechainDepth => "oct".to_string(),
+            SpecfilterParam::PitchTarget => "".to_string(),
+            SpecfilterParam::PitchRatio => "x".to_string(),
+            SpecfilterParam::PitchConfidence => "%".to_string(),
+            SpecfilterParam::PitchGlide => "ms".to_string(),
+            SpecfilterParam::Smoothing => "ms".to_string(),
+            SpecfilterParam::BandwidthUnit => "".to_string(),
+            SpecfilterParam::ResponseType => "".to_string(),
+            SpecfilterParam::SpecMode => "".to_string(),
+            SpecfilterParam::Center => "".to_string(),
+            SpecfilterParam::Width => "oct".to_string(),
+            SpecfilterParam::PassbandEdge1 => "".to_string(),
+            SpecfilterParam::StopbandEdge1 => "".to_string(),
+            SpecfilterParam::PassbandEdge2 => "".to_string(),
+            SpecfilterParam::StopbandEdge2 => "".to_string(),
+            SpecfilterParam::InputGain => "dB".to_string(),
+            SpecfilterParam::OutputGain => "dB".to_string(),
+            SpecfilterParam::MakeupMode => "".to_string(),
+            SpecfilterParam::PhaseCoherentMix => "".to_string(),
+            SpecfilterParam::OutputMode => "".to_string(),
+            SpecfilterParam::Bypass => "".to_string(),
+            SpecfilterParam::BypassReset => "".to_string(),
+            SpecfilterParam::ChannelLayout => "".to_string(),
+            SpecfilterParam::LfeFilter => "".to_string(),
+            SpecfilterParam::StereoMode => "".to_string(),
+            SpecfilterParam::FilterKindB => format!("{}", self.filter_type(SpecGroup::B)),
+            SpecfilterParam::PassbandRippleB => "dB".to_string(),
+            SpecfilterParam::StopbandAttenuationB => "dB".to_string(),
+            SpecfilterParam::Frequency1B => "".to_string(),
+            SpecfilterParam::Frequency2B => "".to_string(),
+            SpecfilterParam::Bandwidth1B => self.bandwidths(SpecGroup::B)[0].label().to_string(),
+            SpecfilterParam::Bandwidth2B => self.bandwidths(SpecGroup::B)[1].label().to_string(),
+            SpecfilterParam::ResponseTypeB => "".to_string(),
+            SpecfilterParam::LinkMode => "".to_string(),
+            SpecfilterParam::Spread => "oct".to_string(),
+            SpecfilterParam::TubeDrive => "dB".to_string(),
+            SpecfilterParam::TubeBias => "".to_string(),
+            SpecfilterParam::TubeOvertones => "%".to_string(),
+            SpecfilterParam::TubeSoftness => "%".to_string(),
+            SpecfilterParam::TubeLowCorner => "".to_string(),
+            SpecfilterParam::TubeHighCorner => "".to_string(),
+            SpecfilterParam::TubePreset => "".to_string(),
         }
     }
 
@@ -370,10 +1599,82 @@ impl PluginParameters for SpecfilterParameters
             SpecfilterParam::PassbandRipple => format!("{:.3}", self.passband_ripple.get()),
             SpecfilterParam::StopbandAttenuation => format!("{:.3}", self.stopband_attenuation.get()),
             SpecfilterParam::Mix => format!("{:.3}", 100.0*self.mix.get()),
-            SpecfilterParam::Frequency1 => format!("{:.3}", self.frequencies[0].get()),
-            SpecfilterParam::Frequency2 => format!("{:.3}", self.frequencies[1].get()),
-            SpecfilterParam::Bandwidth1 => format!("{:.3}", self.bandwidths()[0]),
-            SpecfilterParam::Bandwidth2 => format!("{:.3}", self.bandwidths()[1]),
+            SpecfilterParam::Frequency1 => units::format_frequency(self.frequencies[0].get()),
+            SpecfilterParam::Frequency2 => units::format_frequency(self.frequencies[1].get()),
+            SpecfilterParam::Bandwidth1 => self.bandwidths(SpecGroup::A)[0].format(),
+            SpecfilterParam::Bandwidth2 => self.bandwidths(SpecGroup::A)[1].format(),
+            SpecfilterParam::KeyTarget => format!("{}", self.key_target()),
+            SpecfilterParam::KeyTracking => format!("{:.3}", 100.0*self.key_tracking.get()),
+            SpecfilterParam::PitchBendRange => format!("{:.3}", self.pitch_bend_range.get()),
+            SpecfilterParam::VelocityBandwidth => format!("{:.3}", 100.0*self.velocity_bandwidth.get()),
+            SpecfilterParam::MidiLearn => (if self.midi_learn.load(Ordering::Relaxed) {"On"} else {"Off"}).to_string(),
+            SpecfilterParam::MidiMapParameter => self.get_parameter_name(self.midi_map_parameter() as i32),
+            SpecfilterParam::MidiMapMin => format!("{:.3}", 100.0*self.midi_map[self.midi_map_parameter() as usize].min.get()),
+            SpecfilterParam::MidiMapMax => format!("{:.3}", 100.0*self.midi_map[self.midi_map_parameter() as usize].max.get()),
+            SpecfilterParam::MidiMapCurve => format!("{:.3}", self.midi_map[self.midi_map_parameter() as usize].curve.get()),
+            SpecfilterParam::LfoShape => format!("{}", self.lfo_shape()),
+            SpecfilterParam::LfoSync => (if self.lfo_sync() {"On"} else {"Off"}).to_string(),
+            SpecfilterParam::LfoRate => format!("{:.3}", self.lfo_rate.get()),
+            SpecfilterParam::LfoDivision => self.lfo_division().0.to_string(),
+            SpecfilterParam::LfoDepth => format!("{:.3}", self.lfo_depth.get()),
+            SpecfilterParam::LfoStereoPhase => format!("{:.1}", 360.0*self.lfo_stereo_phase.get()),
+            SpecfilterParam::EnvelopeDetector => format!("{}", self.envelope_detector()),
+            SpecfilterParam::EnvelopeAttack => format!("{:.3}", self.envelope_attack.get()),
+            SpecfilterParam::EnvelopeRelease => format!("{:.3}", self.envelope_release.get()),
+            SpecfilterParam::EnvelopeSensitivity => format!("{:.3}", self.envelope_sensitivity.get()),
+            SpecfilterParam::EnvelopeDepth => format!("{:.3}", self.envelope_depth.get()),
+            SpecfilterParam::EnvelopeBandwidth => format!("{:.3}", 100.0*self.envelope_bandwidth.get()),
+            SpecfilterParam::SidechainMode => format!("{}", self.sidechain_mode()),
+            SpecfilterParam::SidechainFrequency => units::format_frequency(self.sidechain_frequency.get()),
+            SpecfilterParam::SidechainQ => format!("{:.3}", self.sidechain_q.get()),
+            SpecfilterParam::SidechainSensitivity => format!("{:.3}", self.sidechain_sensitivity.get()),
+            SpecfilterParam::SidechainDepth => format!("{:.3}", self.sidechain_depth.get()),
+            SpecfilterParam::PitchTarget => format!("{}", self.pitch_target()),
+            SpecfilterParam::PitchRatio => format!("{:.3}", self.pitch_ratio.get()),
+            SpecfilterParam::PitchConfidence => format!("{:.3}", 100.0*self.pitch_confidence.get()),
+            SpecfilterParam::PitchGlide => format!("{:.3}", self.pitch_glide.get()),
+            SpecfilterParam::Smoothing => format!("{:.3}", self.smoothing.get()),
+            SpecfilterParam::BandwidthUnit => format!("{}", self.bandwidth_unit()),
+            SpecfilterParam::ResponseType => format!("{}", self.response_type()),
+            SpecfilterParam::SpecMode => match self.spec_mode()
+            {
+                SpecMode::Edges => format!("{}", SpecMode::Edges),
+                mode if self.stereo_mode() != StereoMode::Stereo => format!("{}{}", mode, A_ONLY),
+                mode => format!("{}", mode)
+            },
+            SpecfilterParam::Center => units::format_frequency(self.center.get()),
+            SpecfilterParam::Width => format!("{:.3}", self.width.get()),
+            SpecfilterParam::PassbandEdge1 => units::format_frequency(self.edges[0].get()),
+            SpecfilterParam::StopbandEdge1 => units::format_frequency(self.edges[1].get()),
+            SpecfilterParam::PassbandEdge2 => units::format_frequency(self.edges[2].get()),
+            SpecfilterParam::StopbandEdge2 => units::format_frequency(self.edges[3].get()),
+            SpecfilterParam::InputGain => format!("{:.3}", self.input_gain.get()),
+            SpecfilterParam::OutputGain => format!("{:.3}", self.output_gain.get()),
+            SpecfilterParam::MakeupMode => format!("{}", self.makeup_mode()),
+            SpecfilterParam::PhaseCoherentMix => (if self.phase_coherent_mix() {"On"} else {"Off"}).to_string(),
+            SpecfilterParam::OutputMode => format!("{}", self.output_mode()),
+            SpecfilterParam::Bypass => (if self.bypass() {"On"} else {"Off"}).to_string(),
+            SpecfilterParam::BypassReset => (if self.bypass_reset() {"On"} else {"Off"}).to_string(),
+            SpecfilterParam::ChannelLayout => format!("{}", self.channel_layout()),
+            SpecfilterParam::LfeFilter => (if self.lfe_filter() {"On"} else {"Off"}).to_string(),
+            SpecfilterParam::StereoMode => format!("{}", self.stereo_mode()),
+            SpecfilterParam::FilterKindB => format!("{}", self.spec(SpecGroup::B).filter_kind),
+            SpecfilterParam::PassbandRippleB => format!("{:.3}", self.passband_ripple_b.get()),
+            SpecfilterParam::StopbandAttenuationB => format!("{:.3}", self.stopband_attenuation_b.get()),
+            SpecfilterParam::Frequency1B => units::format_frequency(self.frequencies_b[0].get()),
+            SpecfilterParam::Frequency2B => units::format_frequency(self.frequencies_b[1].get()),
+            SpecfilterParam::Bandwidth1B => self.bandwidths(SpecGroup::B)[0].format(),
+            SpecfilterParam::Bandwidth2B => self.bandwidths(SpecGroup::B)[1].format(),
+            SpecfilterParam::ResponseTypeB => format!("{}", self.spec(SpecGroup::B).response_type),
+            SpecfilterParam::LinkMode => format!("{}", self.link_mode()),
+            SpecfilterParam::Spread => format!("{:.3}", self.spread.get()),
+            SpecfilterParam::TubeDrive => format!("{:.3}", self.tube_drive.get()),
+            SpecfilterParam::TubeBias => format!("{:.3}", self.tube_bias.get()),
+            SpecfilterParam::TubeOvertones => format!("{:.3}", 100.0*self.tube_overtones.get()),
+            SpecfilterParam::TubeSoftness => format!("{:.3}", 100.0*self.tube_softness.get()),
+            SpecfilterParam::TubeLowCorner => units::format_frequency(self.tube_corners[0].get()),
+            SpecfilterParam::TubeHighCorner => units::format_frequency(self.tube_corners[1].get()),
+            SpecfilterParam::TubePreset => format!("{}", self.tube_preset()),
         }
     }
 
@@ -389,6 +1690,73 @@ impl PluginParameters for SpecfilterParameters
             SpecfilterParam::Frequency2 => "Frequency 2".to_string(),
             SpecfilterParam::Bandwidth1 => "Bandwidth 1".to_string(),
             SpecfilterParam::Bandwidth2 => "Bandwidth 2".to_string(),
+            SpecfilterParam::KeyTarget => "Key target".to_string(),
+            SpecfilterParam::KeyTracking => "Key tracking".to_string(),
+            SpecfilterParam::PitchBendRange => "Pitch bend range".to_string(),
+            SpecfilterParam::VelocityBandwidth => "Velocity to bandwidth".to_string(),
+            SpecfilterParam::MidiLearn => "MIDI learn".to_string(),
+            SpecfilterParam::MidiMapParameter => "MIDI map parameter".to_string(),
+            SpecfilterParam::MidiMapMin => "MIDI map min".to_string(),
+            SpecfilterParam::MidiMapMax => "MIDI map max".to_string(),
+            SpecfilterParam::MidiMapCurve => "MIDI map curve".to_string(),
+            SpecfilterParam::LfoShape => "LFO shape".to_string(),
+            SpecfilterParam::LfoSync => "LFO sync".to_string(),
+            SpecfilterParam::LfoRate => "LFO rate".to_string(),
+            SpecfilterParam::LfoDivision => "LFO division".to_string(),
+            SpecfilterParam::LfoDepth => "LFO depth".to_string(),
+            SpecfilterParam::LfoStereoPhase => "LFO stereo phase".to_string(),
+            SpecfilterParam::EnvelopeDetector => "Envelope detector".to_string(),
+            SpecfilterParam::EnvelopeAttack => "Envelope attack".to_string(),
+            SpecfilterParam::EnvelopeRelease => "Envelope release".to_string(),
+            SpecfilterParam::EnvelopeSensitivity => "Envelope sensitivity".to_string(),
+            SpecfilterParam::EnvelopeDepth => "Envelope depth".to_string(),
+            SpecfilterParam::EnvelopeBandwidth => "Envelope to bandwidth".to_string(),
+            SpecfilterParam::SidechainMode => "Sidechain mode".to_string(),
+            SpecfilterParam::SidechainFrequency => "Sidechain frequency".to_string(),
+            SpecfilterParam::SidechainQ => "Sidechain Q".to_string(),
+            SpecfilterParam::SidechainSensitivity => "Sidechain sensitivity".to_string(),
+            SpecfilterParam::SidechainDepth => "Sidechain depth".to_string(),
+            SpecfilterParam::PitchTarget => "Pitch target".to_string(),
+            SpecfilterParam::PitchRatio => "Pitch ratio".to_string(),
+            SpecfilterParam::PitchConfidence => "Pitch confidence".to_string(),
+            SpecfilterParam::PitchGlide => "Pitch glide".to_string(),
+            SpecfilterParam::Smoothing => "Smoothing".to_string(),
+            SpecfilterParam::BandwidthUnit => "Bandwidth unit".to_string(),
+            SpecfilterParam::ResponseType => "Response type".to_string(),
+            SpecfilterParam::SpecMode => format!("Spec mode{}", A_ONLY),
+            SpecfilterParam::Center => format!("Center{}", A_ONLY),
+            SpecfilterParam::Width => format!("Width{}", A_ONLY),
+            SpecfilterParam::PassbandEdge1 => format!("Passband edge 1{}", A_ONLY),
+            SpecfilterParam::StopbandEdge1 => format!("Stopband edge 1{}", A_ONLY),
+            SpecfilterParam::PassbandEdge2 => format!("Passband edge 2{}", A_ONLY),
+            SpecfilterParam::StopbandEdge2 => format!("Stopband edge 2{}", A_ONLY),
+            SpecfilterParam::InputGain => "Input gain".to_string(),
+            SpecfilterParam::OutputGain => "Output gain".to_string(),
+            SpecfilterParam::MakeupMode => "Makeup".to_string(),
+            SpecfilterParam::PhaseCoherentMix => "Phase-coherent mix".to_string(),
+            SpecfilterParam::OutputMode => "Output mode".to_string(),
+            SpecfilterParam::Bypass => "Bypass".to_string(),
+            SpecfilterParam::BypassReset => "Reset on bypass".to_string(),
+            SpecfilterParam::ChannelLayout => "Channel layout".to_string(),
+            SpecfilterParam::LfeFilter => "Filter LFE".to_string(),
+            SpecfilterParam::StereoMode => "Stereo mode".to_string(),
+            SpecfilterParam::FilterKindB => "Filter type B".to_string(),
+            SpecfilterParam::PassbandRippleB => "Passband ripple B".to_string(),
+            SpecfilterParam::StopbandAttenuationB => "Stopband attenuation B".to_string(),
+            SpecfilterParam::Frequency1B => "Frequency 1 B".to_string(),
+            SpecfilterParam::Frequency2B => "Frequency 2 B".to_string(),
+            SpecfilterParam::Bandwidth1B => "Bandwidth 1 B".to_string(),
+            SpecfilterParam::Bandwidth2B => "Bandwidth 2 B".to_string(),
+            SpecfilterParam::ResponseTypeB => "Response type B".to_string(),
+            SpecfilterParam::LinkMode => "Link".to_string(),
+            SpecfilterParam::Spread => "Stereo spread".to_string(),
+            SpecfilterParam::TubeDrive => "Tube drive".to_string(),
+            SpecfilterParam::TubeBias => "Tube bias".to_string(),
+            SpecfilterParam::TubeOvertones => "Tube overtones".to_string(),
+            SpecfilterParam::TubeSoftness => "Tube softness".to_string(),
+            SpecfilterParam::TubeLowCorner => "Tube low corner".to_string(),
+            SpecfilterParam::TubeHighCorner => "Tube high corner".to_string(),
+            SpecfilterParam::TubePreset => "Tube character".to_string(),
         }
     }
 
@@ -411,76 +1779,369 @@ impl PluginParameters for SpecfilterParameters
             },
             SpecfilterParam::Bandwidth1 => (self.bandwidths[0].get() + 1.0)*0.5,
             SpecfilterParam::Bandwidth2 => (self.bandwidths[1].get() + 1.0)*0.5,
+            SpecfilterParam::KeyTarget => self.key_target.load(Ordering::Relaxed) as f32/(KeyTarget::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::KeyTracking => self.key_tracking.get(),
+            SpecfilterParam::PitchBendRange => self.pitch_bend_range.get()/MAX_PITCH_BEND_RANGE,
+            SpecfilterParam::VelocityBandwidth => self.velocity_bandwidth.get(),
+            SpecfilterParam::MidiLearn => self.midi_learn.load(Ordering::Relaxed) as u8 as f32,
+            SpecfilterParam::MidiMapParameter => SpecfilterParam::mappable()
+                .position(|v| v == self.midi_map_parameter())
+                .unwrap_or_default() as f32/(SpecfilterParam::mappable().count() - 1) as f32,
+            SpecfilterParam::MidiMapMin => self.midi_map[self.midi_map_parameter() as usize].min.get(),
+            SpecfilterParam::MidiMapMax => self.midi_map[self.midi_map_parameter() as usize].max.get(),
+            SpecfilterParam::MidiMapCurve => (self.midi_map[self.midi_map_parameter() as usize].curve.get() + 1.0)*0.5,
+            SpecfilterParam::LfoShape => self.lfo_shape.load(Ordering::Relaxed) as f32/(LfoShape::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::LfoSync => self.lfo_sync() as u8 as f32,
+            SpecfilterParam::LfoRate => (self.lfo_rate.get().log2() - MIN_LFO_RATE.log2())/(MAX_LFO_RATE.log2() - MIN_LFO_RATE.log2()),
+            SpecfilterParam::LfoDivision => self.lfo_division.load(Ordering::Relaxed) as f32/(LFO_DIVISION_COUNT - 1) as f32,
+            SpecfilterParam::LfoDepth => self.lfo_depth.get()/MAX_LFO_DEPTH,
+            SpecfilterParam::LfoStereoPhase => self.lfo_stereo_phase.get(),
+            SpecfilterParam::EnvelopeDetector => self.envelope_detector.load(Ordering::Relaxed) as f32/(EnvelopeDetector::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::EnvelopeAttack => log_normalize(self.envelope_attack.get(), MIN_ATTACK, MAX_ATTACK),
+            SpecfilterParam::EnvelopeRelease => log_normalize(self.envelope_release.get(), MIN_RELEASE, MAX_RELEASE),
+            SpecfilterParam::EnvelopeSensitivity => self.envelope_sensitivity.get()/MAX_SENSITIVITY,
+            SpecfilterParam::EnvelopeDepth => (self.envelope_depth.get()/MAX_ENVELOPE_DEPTH + 1.0)*0.5,
+            SpecfilterParam::EnvelopeBandwidth => (self.envelope_bandwidth.get() + 1.0)*0.5,
+            SpecfilterParam::SidechainMode => self.sidechain_mode.load(Ordering::Relaxed) as f32/(SidechainMode::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::SidechainFrequency => log_normalize(self.sidechain_frequency.get(), MIN_FREQ, MAX_FREQ),
+            SpecfilterParam::SidechainQ => log_normalize(self.sidechain_q.get(), MIN_SIDECHAIN_Q, MAX_SIDECHAIN_Q),
+            SpecfilterParam::SidechainSensitivity => self.sidechain_sensitivity.get()/MAX_SENSITIVITY,
+            SpecfilterParam::SidechainDepth => (self.sidechain_depth.get()/MAX_ENVELOPE_DEPTH + 1.0)*0.5,
+            SpecfilterParam::PitchTarget => self.pitch_target.load(Ordering::Relaxed) as f32/(PitchTarget::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::PitchRatio => log_normalize(self.pitch_ratio.get(), MIN_PITCH_RATIO, MAX_PITCH_RATIO),
+            SpecfilterParam::PitchConfidence => self.pitch_confidence.get(),
+            SpecfilterParam::PitchGlide => log_normalize(self.pitch_glide.get(), MIN_GLIDE, MAX_GLIDE),
+            SpecfilterParam::Smoothing => log_normalize(self.smoothing.get(), MIN_SMOOTHING, MAX_SMOOTHING),
+            SpecfilterParam::BandwidthUnit => self.bandwidth_unit.load(Ordering::Relaxed) as f32/(BandwidthUnit::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::ResponseType => self.response_type.load(Ordering::Relaxed) as f32/(ResponseType::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::SpecMode => self.spec_mode.load(Ordering::Relaxed) as f32/(SpecMode::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::Center => self.normalize_frequency(self.center.get()),
+            SpecfilterParam::Width => log_normalize(self.width.get(), MIN_WIDTH, MAX_WIDTH),
+            SpecfilterParam::PassbandEdge1 => self.normalize_frequency(self.edges[0].get()),
+            SpecfilterParam::StopbandEdge1 => self.normalize_frequency(self.edges[1].get()),
+            SpecfilterParam::PassbandEdge2 => self.normalize_frequency(self.edges[2].get()),
+            SpecfilterParam::StopbandEdge2 => self.normalize_frequency(self.edges[3].get()),
+            SpecfilterParam::InputGain => (self.input_gain.get()/MAX_GAIN + 1.0)*0.5,
+            SpecfilterParam::OutputGain => (self.output_gain.get()/MAX_GAIN + 1.0)*0.5,
+            SpecfilterParam::MakeupMode => self.makeup_mode.load(Ordering::Relaxed) as f32/(MakeupMode::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::PhaseCoherentMix => self.phase_coherent_mix() as u8 as f32,
+            SpecfilterParam::OutputMode => self.output_mode.load(Ordering::Relaxed) as f32/(OutputMode::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::Bypass => self.bypass() as u8 as f32,
+            SpecfilterParam::BypassReset => self.bypass_reset() as u8 as f32,
+            SpecfilterParam::ChannelLayout => self.channel_layout.load(Ordering::Relaxed) as f32/(ChannelLayout::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::LfeFilter => self.lfe_filter() as u8 as f32,
+            SpecfilterParam::StereoMode => self.stereo_mode.load(Ordering::Relaxed) as f32/(StereoMode::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::FilterKindB => self.filter_kind_b.load(Ordering::Relaxed) as f32/(FilterKind::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::PassbandRippleB => (self.passband_ripple_b.get() - MIN_RIPPLE)/(MAX_RIPPLE - MIN_RIPPLE),
+            SpecfilterParam::StopbandAttenuationB => (self.stopband_attenuation_b.get() - MIN_RIPPLE)/(MAX_RIPPLE - MIN_RIPPLE),
+            SpecfilterParam::Frequency1B => self.normalize_frequency(self.frequencies_b[0].get()),
+            SpecfilterParam::Frequency2B => self.normalize_frequency(self.frequencies_b[1].get()),
+            SpecfilterParam::Bandwidth1B => (self.bandwidths_b[0].get() + 1.0)*0.5,
+            SpecfilterParam::Bandwidth2B => (self.bandwidths_b[1].get() + 1.0)*0.5,
+            SpecfilterParam::ResponseTypeB => self.response_type_b.load(Ordering::Relaxed) as f32/(ResponseType::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::LinkMode => self.link_mode.load(Ordering::Relaxed) as f32/(LinkMode::VARIANT_COUNT - 1) as f32,
+            SpecfilterParam::Spread => (self.spread.get()/MAX_SPREAD + 1.0)*0.5,
+            SpecfilterParam::TubeDrive => (self.tube_drive.get()/MAX_GAIN + 1.0)*0.5,
+            SpecfilterParam::TubeBias => (self.tube_bias.get()/MAX_TUBE_BIAS + 1.0)*0.5,
+            SpecfilterParam::TubeOvertones => self.tube_overtones.get()/MAX_TUBE_SHAPE,
+            SpecfilterParam::TubeSoftness => self.tube_softness.get()/MAX_TUBE_SHAPE,
+            SpecfilterParam::TubeLowCorner => log_normalize(self.tube_corners[0].get(), MIN_TUBE_LOW_CORNER, MAX_TUBE_LOW_CORNER),
+            SpecfilterParam::TubeHighCorner => log_normalize(self.tube_corners[1].get(), MIN_TUBE_HIGH_CORNER, MAX_TUBE_HIGH_CORNER),
+            SpecfilterParam::TubePreset => self.tube_preset.load(Ordering::Relaxed) as f32/(TubePreset::VARIANT_COUNT - 1) as f32,
         }.min(1.0).max(0.0)
     }
     
     fn set_parameter(&self, index: i32, value: f32)
     {
-        match SpecfilterParam::VARIANTS[index as usize]
+        if let Some(linked) = self.apply_parameter(SpecfilterParam::VARIANTS[index as usize], value)
         {
-            SpecfilterParam::FilterKind => self.filter_kind.store((value*(FilterKind::VARIANT_COUNT - 1) as f32).round() as u8, Ordering::Relaxed),
-            SpecfilterParam::PassbandRipple => self.passband_ripple.set(value*(MAX_RIPPLE - MIN_RIPPLE) + MIN_RIPPLE),
-            SpecfilterParam::StopbandAttenuation => self.stopband_attenuation.set(value*(MAX_RIPPLE - MIN_RIPPLE) + MIN_RIPPLE),
-            SpecfilterParam::Mix => self.mix.set(value),
-            SpecfilterParam::Frequency1 => {
-                let max_freq = MAX_FREQ.min(self.rate.get()/2.0);
-                self.frequencies[0].set((value*1.000001*(max_freq.log2() - MIN_FREQ.log2()) + MIN_FREQ.log2()).exp2().min(max_freq).max(MIN_FREQ))
-            },
-            SpecfilterParam::Frequency2 => {
-                let max_freq = MAX_FREQ.min(self.rate.get()/2.0);
-                self.frequencies[1].set((value*1.000001*(max_freq.log2() - MIN_FREQ.log2()) + MIN_FREQ.log2()).exp2().min(max_freq).max(MIN_FREQ))
+            self.linked_changed[linked as usize].store(true, Ordering::Relaxed)
+        }
+    }
+
+    fn string_to_parameter(&self, index: i32, text: String) -> bool
+    {
+        let value = match SpecfilterParam::VARIANTS[index as usize]
+        {
+            SpecfilterParam::FilterKind | SpecfilterParam::FilterKindB => FilterKind::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|kind| kind as f32/(FilterKind::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::PassbandRipple | SpecfilterParam::StopbandAttenuation | SpecfilterParam::PassbandRippleB | SpecfilterParam::StopbandAttenuationB => units::parse_decibels(&text)
+                .map(|r| (r.abs() - MIN_RIPPLE)/(MAX_RIPPLE - MIN_RIPPLE)),
+            SpecfilterParam::Mix => units::parse_percent(&text)
+                .map(|mix| mix/100.0),
+            SpecfilterParam::Frequency1 | SpecfilterParam::Frequency2 | SpecfilterParam::Frequency1B | SpecfilterParam::Frequency2B => units::parse_frequency(&text)
+                .filter(|&f| f > 0.0)
+                .map(|f| self.normalize_frequency(f)),
+            SpecfilterParam::Bandwidth1 => units::parse_width(&text, self.bandwidth_unit())
+                .map(|w| self.normalize_bandwidth(SpecGroup::A, 0, w)),
+            SpecfilterParam::Bandwidth2 => units::parse_width(&text, self.bandwidth_unit())
+                .map(|w| self.normalize_bandwidth(SpecGroup::A, 1, w)),
+            SpecfilterParam::Bandwidth1B => units::parse_width(&text, self.bandwidth_unit())
+                .map(|w| self.normalize_bandwidth(SpecGroup::B, 0, w)),
+            SpecfilterParam::Bandwidth2B => units::parse_width(&text, self.bandwidth_unit())
+                .map(|w| self.normalize_bandwidth(SpecGroup::B, 1, w)),
+            SpecfilterParam::KeyTarget => KeyTarget::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|target| target as f32/(KeyTarget::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::KeyTracking | SpecfilterParam::VelocityBandwidth => units::parse_percent(&text)
+                .map(|amount| amount/100.0),
+            SpecfilterParam::PitchBendRange => units::parse_semitones(&text)
+                .map(|range| range.abs()/MAX_PITCH_BEND_RANGE),
+            SpecfilterParam::MidiLearn | SpecfilterParam::LfoSync | SpecfilterParam::PhaseCoherentMix | SpecfilterParam::Bypass | SpecfilterParam::BypassReset | SpecfilterParam::LfeFilter => units::parse_switch(&text)
+                .map(|on| on as u8 as f32),
+            SpecfilterParam::MidiMapParameter => SpecfilterParam::mappable()
+                .position(|v| self.get_parameter_name(v as i32).eq_ignore_ascii_case(text.trim()))
+                .map(|v| v as f32/(SpecfilterParam::mappable().count() - 1) as f32),
+            SpecfilterParam::MidiMapMin | SpecfilterParam::MidiMapMax => units::parse_percent(&text)
+                .map(|amount| amount/100.0),
+            SpecfilterParam::MidiMapCurve => text.trim()
+                .parse::<f32>()
+                .ok()
+                .map(|curve| (curve + 1.0)*0.5),
+            SpecfilterParam::LfoShape => LfoShape::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|shape| shape as f32/(LfoShape::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::LfoRate => units::parse_frequency(&text)
+                .filter(|&f| f > 0.0)
+                .map(|f| (f.log2() - MIN_LFO_RATE.log2())/(MAX_LFO_RATE.log2() - MIN_LFO_RATE.log2())),
+            SpecfilterParam::LfoDivision => LFO_DIVISIONS.iter()
+                .position(|(name, _)| name.eq_ignore_ascii_case(text.trim()))
+                .map(|division| division as f32/(LFO_DIVISION_COUNT - 1) as f32),
+            SpecfilterParam::LfoDepth => units::parse_octaves(&text)
+                .map(|depth| depth.abs()/MAX_LFO_DEPTH),
+            SpecfilterParam::LfoStereoPhase => units::parse_degrees(&text)
+                .map(|phase| phase.rem_euclid(360.0)/360.0),
+            SpecfilterParam::EnvelopeDetector => EnvelopeDetector::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|detector| detector as f32/(EnvelopeDetector::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::EnvelopeAttack => units::parse_milliseconds(&text)
+                .filter(|&t| t > 0.0)
+                .map(|t| log_normalize(t, MIN_ATTACK, MAX_ATTACK)),
+            SpecfilterParam::EnvelopeRelease => units::parse_milliseconds(&text)
+                .filter(|&t| t > 0.0)
+                .map(|t| log_normalize(t, MIN_RELEASE, MAX_RELEASE)),
+            SpecfilterParam::EnvelopeSensitivity => units::parse_decibels(&text)
+                .map(|sensitivity| sensitivity/MAX_SENSITIVITY),
+            SpecfilterParam::EnvelopeDepth => units::parse_octaves(&text)
+                .map(|depth| (depth/MAX_ENVELOPE_DEPTH + 1.0)*0.5),
+            SpecfilterParam::EnvelopeBandwidth => units::parse_percent(&text)
+                .map(|amount| (amount/100.0 + 1.0)*0.5),
+            SpecfilterParam::SidechainMode => SidechainMode::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|mode| mode as f32/(SidechainMode::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::SidechainFrequency => units::parse_frequency(&text)
+                .filter(|&f| f > 0.0)
+                .map(|f| log_normalize(f, MIN_FREQ, MAX_FREQ)),
+            SpecfilterParam::SidechainQ => units::parse_width(&text, BandwidthUnit::Q)
+                .and_then(|width| match width
+                {
+                    Width::Q(q) => Some(q),
+                    _ => None
+                })
+                .filter(|&q| q > 0.0)
+                .map(|q| log_normalize(q, MIN_SIDECHAIN_Q, MAX_SIDECHAIN_Q)),
+            SpecfilterParam::SidechainSensitivity => units::parse_decibels(&text)
+                .map(|sensitivity| sensitivity/MAX_SENSITIVITY),
+            SpecfilterParam::SidechainDepth => units::parse_octaves(&text)
+                .map(|depth| (depth/MAX_ENVELOPE_DEPTH + 1.0)*0.5),
+            SpecfilterParam::PitchTarget => PitchTarget::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|target| target as f32/(PitchTarget::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::PitchRatio => text.trim()
+                .trim_end_matches(['x', 'X'])
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|&ratio| ratio > 0.0)
+                .map(|ratio| log_normalize(ratio, MIN_PITCH_RATIO, MAX_PITCH_RATIO)),
+            SpecfilterParam::PitchConfidence => units::parse_percent(&text)
+                .map(|confidence| confidence/100.0),
+            SpecfilterParam::PitchGlide => units::parse_milliseconds(&text)
+                .filter(|&t| t > 0.0)
+                .map(|t| log_normalize(t, MIN_GLIDE, MAX_GLIDE)),
+            SpecfilterParam::Smoothing => units::parse_milliseconds(&text)
+                .filter(|&t| t > 0.0)
+                .map(|t| log_normalize(t, MIN_SMOOTHING, MAX_SMOOTHING)),
+            SpecfilterParam::BandwidthUnit => BandwidthUnit::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|unit| unit as f32/(BandwidthUnit::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::ResponseType | SpecfilterParam::ResponseTypeB => ResponseType::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|response| response as f32/(ResponseType::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::SpecMode => SpecMode::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim().trim_end_matches(A_ONLY)))
+                .map(|mode| mode as f32/(SpecMode::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::Center => units::parse_frequency(&text)
+                .filter(|&f| f > 0.0)
+                .map(|f| self.normalize_frequency(f)),
+            SpecfilterParam::Width => units::parse_width(&text, BandwidthUnit::Octaves)
+                .and_then(|w| self.width_octaves(w))
+                .filter(|&o| o > 0.0)
+                .map(|o| log_normalize(o, MIN_WIDTH, MAX_WIDTH)),
+            SpecfilterParam::PassbandEdge1 | SpecfilterParam::StopbandEdge1 | SpecfilterParam::PassbandEdge2 | SpecfilterParam::StopbandEdge2 => units::parse_frequency(&text)
+                .filter(|&f| f > 0.0)
+                .map(|f| self.normalize_frequency(f)),
+            SpecfilterParam::InputGain | SpecfilterParam::OutputGain => units::parse_decibels(&text)
+                .map(|gain| (gain/MAX_GAIN + 1.0)*0.5),
+            SpecfilterParam::MakeupMode => MakeupMode::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|mode| mode as f32/(MakeupMode::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::OutputMode => OutputMode::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|mode| mode as f32/(OutputMode::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::ChannelLayout => ChannelLayout::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|layout| layout as f32/(ChannelLayout::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::StereoMode => StereoMode::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|mode| mode as f32/(StereoMode::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::LinkMode => LinkMode::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|mode| mode as f32/(LinkMode::VARIANT_COUNT - 1) as f32),
+            SpecfilterParam::Spread => units::parse_octaves(&text)
+                .map(|spread| (spread/MAX_SPREAD + 1.0)*0.5),
+            SpecfilterParam::TubeDrive => units::parse_decibels(&text)
+                .map(|drive| (drive/MAX_GAIN + 1.0)*0.5),
+            SpecfilterParam::TubeBias => text.trim()
+                .parse::<f32>()
+                .ok()
+                .map(|bias| (bias/MAX_TUBE_BIAS + 1.0)*0.5),
+            SpecfilterParam::TubeOvertones | SpecfilterParam::TubeSoftness => units::parse_percent(&text)
+                .map(|amount| amount/100.0/MAX_TUBE_SHAPE),
+            SpecfilterParam::TubeLowCorner => units::parse_frequency(&text)
+                .filter(|&f| f > 0.0)
+                .map(|f| log_normalize(f, MIN_TUBE_LOW_CORNER, MAX_TUBE_LOW_CORNER)),
+            SpecfilterParam::TubeHighCorner => units::parse_frequency(&text)
+                .filter(|&f| f > 0.0)
+                .map(|f| log_normalize(f, MIN_TUBE_HIGH_CORNER, MAX_TUBE_HIGH_CORNER)),
+            SpecfilterParam::TubePreset => TubePreset::VARIANT_NAMES.iter()
+                .position(|name| name.eq_ignore_ascii_case(text.trim()))
+                .map(|preset| preset as f32/(TubePreset::VARIANT_COUNT - 1) as f32),
+        };
+
+        match value
+        {
+            Some(value) if value.is_finite() => {
+                self.set_parameter(index, value.min(1.0).max(0.0));
+                true
             },
-            SpecfilterParam::Bandwidth1 => self.bandwidths[0].set(value*2.0 - 1.0),
-            SpecfilterParam::Bandwidth2 => self.bandwidths[1].set(value*2.0 - 1.0),
+            _ => false
         }
     }
 
-    fn change_preset(&self, preset: i32) {}
+    fn change_preset(&self, preset: i32)
+    {
+        let mut presets = self.presets.lock().unwrap();
+        if preset < 0 || preset as usize >= presets.len()
+        {
+            return
+        }
+        self.store_preset(&mut presets);
+        self.preset.store(preset as usize, Ordering::Relaxed);
+        self.load_values(&presets[preset as usize].values);
+    }
 
     fn get_preset_num(&self) -> i32
     {
-        0
+        self.preset.load(Ordering::Relaxed) as i32
     }
 
-    fn set_preset_name(&self, name: String) {}
+    fn set_preset_name(&self, name: String)
+    {
+        let mut presets = self.presets.lock().unwrap();
+        if let Some(preset) = presets.get_mut(self.preset.load(Ordering::Relaxed))
+        {
+            preset.name = name
+        }
+    }
 
     fn get_preset_name(&self, preset: i32) -> String
     {
-        "".to_string()
+        self.presets.lock()
+            .unwrap()
+            .get(preset as usize)
+            .map(|preset| preset.name.clone())
+            .unwrap_or_default()
     }
 
     fn can_be_automated(&self, index: i32) -> bool
     {
-        index < SpecfilterParam::VARIANT_COUNT as i32
+        SpecfilterParam::VARIANTS.get(index as usize)
+            .is_some_and(|v| !matches!(v, SpecfilterParam::MidiMapMin | SpecfilterParam::MidiMapMax | SpecfilterParam::MidiMapCurve | SpecfilterParam::ChannelLayout | SpecfilterParam::Bypass))
     }
 
     fn get_preset_data(&self) -> Vec<u8>
     {
-        SpecfilterParam::VARIANTS.map(|v| self.get_parameter(v as i32).to_le_bytes())
-            .concat()
+        let mut presets = self.presets.lock().unwrap();
+        self.store_preset(&mut presets);
+        presets.get(self.preset.load(Ordering::Relaxed))
+            .map(|preset| preset.to_bytes())
+            .unwrap_or_default()
     }
 
     fn get_bank_data(&self) -> Vec<u8>
     {
-        SpecfilterParam::VARIANTS.map(|v| self.get_parameter(v as i32).to_le_bytes())
-            .concat()
+        let mut presets = self.presets.lock().unwrap();
+        self.store_preset(&mut presets);
+        [
+            (presets.len() as u32).to_le_bytes(),
+            (self.preset.load(Ordering::Relaxed) as u32).to_le_bytes()
+        ].concat()
+            .into_iter()
+            .chain(presets.iter()
+                .flat_map(|preset| preset.to_bytes())
+            ).chain(self.midi_map_bytes())
+            .chain(self.layout_bytes())
+            .collect()
     }
 
-    fn load_preset_data(&self, data: &[u8])
+    fn load_preset_data(&self, mut data: &[u8])
     {
-        for (v, &b) in SpecfilterParam::VARIANTS.into_iter()
-            .zip(data.array_chunks())
+        let mut presets = self.presets.lock().unwrap();
+        if let Some(loaded) = SpecfilterPreset::from_bytes(&mut data)
         {
-            self.set_parameter(v as i32, f32::from_le_bytes(b));
+            self.load_values(&loaded.values);
+            if let Some(preset) = presets.get_mut(self.preset.load(Ordering::Relaxed))
+            {
+                *preset = loaded;
+                self.store_preset(&mut presets);
+            }
         }
     }
 
-    fn load_bank_data(&self, data: &[u8])
+    fn load_bank_data(&self, mut data: &[u8])
     {
-        for (v, &b) in SpecfilterParam::VARIANTS.into_iter()
-            .zip(data.array_chunks())
+        let mut presets = self.presets.lock().unwrap();
+        let (Some(count), Some(current)) = (preset::read_u32(&mut data), preset::read_u32(&mut data))
+        else
+        {
+            return
+        };
+        let mut complete = true;
+        for i in 0..count as usize
+        {
+            let Some(loaded) = SpecfilterPreset::from_bytes(&mut data)
+            else
+            {
+                complete = false;
+                break
+            };
+            if let Some(preset) = presets.get_mut(i)
+            {
+                *preset = loaded
+            }
+        }
+        let current = (current as usize).min(presets.len().saturating_sub(1));
+        self.preset.store(current, Ordering::Relaxed);
+        if let Some(preset) = presets.get(current)
+        {
+            self.load_values(&preset.values);
+        }
+        if complete
         {
-            self.set_parameter(v as i32, f32::from_le_bytes(b));
+            self.load_midi_map_bytes(&mut data);
+            self.load_layout_bytes(&mut data);
         }
     }
 }
\ No newline at end of file
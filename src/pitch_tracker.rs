@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use crate::parameters::{SpecfilterParamData, SpecfilterParameters};
+use crate::pitch_target::PitchTarget;
+
+const MIN_PITCH: f64 = 40.0;
+const MAX_PITCH: f64 = 2000.0;
+const YIN_THRESHOLD: f64 = 0.15;
+const SILENCE: f64 = 1e-8;
+const HOP: usize = 256;
+/// The input is decimated down to at least this rate before detection, which keeps the cost of the difference function independent of the sample rate.
+const ANALYSIS_RATE: f64 = 4.0*MAX_PITCH;
+
+/// Monophonic pitch detector for the input, using the YIN difference function.
+/// The pitch is only updated when the detection is confident enough, and glides towards it in the log-frequency domain.
+pub struct PitchTracker
+{
+    decimation: usize,
+    window: usize,
+    accumulator: f64,
+    count: usize,
+    buffer: VecDeque<f64>,
+    difference: Vec<f64>,
+    hop: usize,
+    target: Option<f32>,
+    pitch: Option<f32>
+}
+
+impl PitchTracker
+{
+    pub fn new(rate: f64) -> Self
+    {
+        let decimation = ((rate/ANALYSIS_RATE).floor() as usize).max(1);
+        let window = 2*(rate/decimation as f64/MIN_PITCH).ceil() as usize;
+        Self {
+            decimation,
+            window,
+            accumulator: 0.0,
+            count: 0,
+            buffer: VecDeque::with_capacity(window + 1),
+            difference: vec![1.0; window/2 + 1],
+            hop: 0,
+            target: None,
+            pitch: None
+        }
+    }
+
+    /// Reallocates the analysis buffers for a new sample rate. This also resets the tracker.
+    pub fn set_rate(&mut self, rate: f64)
+    {
+        *self = Self::new(rate)
+    }
+
+    pub fn reset(&mut self)
+    {
+        self.accumulator = 0.0;
+        self.count = 0;
+        self.buffer.clear();
+        self.hop = 0;
+        self.target = None;
+        self.pitch = None;
+    }
+
+    /// The tracked fundamental in Hz, if any pitch has been detected yet.
+    pub fn pitch(&self) -> Option<f32>
+    {
+        self.pitch.map(f32::exp2)
+    }
+
+    pub fn process(&mut self, param: &SpecfilterParameters, x: &[Vec<f64>], block: Range<usize>, rate: f64)
+    {
+        if param.pitch_target() == PitchTarget::Off || x.is_empty()
+        {
+            self.reset();
+            return
+        }
+
+        let confidence = param.pitch_confidence.get() as f64;
+
+        for i in block
+        {
+            self.accumulator += x.iter()
+                .map(|x| x[i])
+                .sum::<f64>()/x.len() as f64;
+            self.count += 1;
+
+            self.hop += 1;
+            if self.count < self.decimation
+            {
+                continue
+            }
+            // Averaging over the decimation factor doubles as a crude anti-aliasing filter, which is enough for a pitch estimate
+            self.buffer.push_back(self.accumulator/self.count as f64);
+            self.accumulator = 0.0;
+            self.count = 0;
+            while self.buffer.len() > self.window
+            {
+                self.buffer.pop_front();
+            }
+
+            if self.hop >= HOP && self.buffer.len() == self.window
+            {
+                self.hop = 0;
+                if let Some((f, c)) = self.detect(rate/self.decimation as f64)
+                {
+                    if c >= confidence
+                    {
+                        self.target = Some(f.log2() as f32)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Glides the tracked pitch towards the last confident detection over the given amount of samples.
+    pub fn glide(&mut self, param: &SpecfilterParameters, samples: usize, rate: f64)
+    {
+        let glide = (-(samples as f64)/(param.pitch_glide.get() as f64/1000.0*rate)).exp() as f32;
+        self.pitch = match (self.pitch, self.target)
+        {
+            (Some(pitch), Some(target)) => Some(target + (pitch - target)*glide),
+            (pitch, target) => target.or(pitch)
+        };
+    }
+
+    /// Returns the detected fundamental and the confidence of the detection, from 0.0 to 1.0.
+    fn detect(&mut self, rate: f64) -> Option<(f64, f64)>
+    {
+        let x = self.buffer.make_contiguous();
+        let d = &mut self.difference;
+        let tau_max = x.len()/2;
+        let tau_min = ((rate/MAX_PITCH).floor() as usize).max(2);
+
+        if x[..tau_max].iter()
+            .map(|x| x*x)
+            .sum::<f64>() < SILENCE*tau_max as f64
+        {
+            return None
+        }
+
+        // Cumulative mean normalized difference function
+        d[0] = 1.0;
+        let mut sum = 0.0;
+        for tau in 1..=tau_max
+        {
+            let diff: f64 = x[..tau_max].iter()
+                .zip(&x[tau..tau + tau_max])
+                .map(|(a, b)| (a - b)*(a - b))
+                .sum();
+            sum += diff;
+            d[tau] = if sum > 0.0 {diff*tau as f64/sum} else {1.0};
+        }
+
+        let mut tau = match (tau_min..tau_max).find(|&tau| d[tau] < YIN_THRESHOLD)
+        {
+            Some(tau) => tau,
+            None => (tau_min..tau_max).min_by(|&a, &b| d[a].total_cmp(&d[b]))?
+        };
+        while tau + 1 < tau_max && d[tau + 1] < d[tau]
+        {
+            tau += 1
+        }
+
+        // Parabolic interpolation around the minimum
+        let (a, b, c) = (d[tau - 1], d[tau], d[tau + 1]);
+        let denom = a - 2.0*b + c;
+        let offset = if denom.abs() > f64::EPSILON {(0.5*(a - c)/denom).max(-0.5).min(0.5)} else {0.0};
+
+        let f = rate/(tau as f64 + offset);
+        if !f.is_finite() || f < MIN_PITCH || f > MAX_PITCH
+        {
+            return None
+        }
+        Some((f, (1.0 - b).max(0.0).min(1.0)))
+    }
+
+    /// Keys the band to the tracked fundamental times the pitch ratio.
+    /// In harmonic mode the band edges are put on the harmonic given by the ratio and the one above it.
+    pub fn track(&self, param: &SpecfilterParameters, data: &mut SpecfilterParamData, rate: f32)
+    {
+        let Some(pitch) = self.pitch
+        else
+        {
+            return
+        };
+        let ratio = param.pitch_ratio.get();
+
+        match param.pitch_target()
+        {
+            PitchTarget::Off => (),
+            PitchTarget::Center => if let Some(center) = data.center(rate)
+            {
+                let octaves = pitch + ratio.log2() - center.log2();
+                data.shift_octaves([octaves; 2], rate)
+            },
+            PitchTarget::Harmonics => {
+                let lower = pitch + ratio.log2();
+                let upper = pitch + (ratio + 1.0).log2();
+                let [f0, f1] = if data.frequencies[0] <= data.frequencies[1] {[lower, upper]} else {[upper, lower]};
+                data.shift_octaves([f0 - data.frequencies[0].log2(), f1 - data.frequencies[1].log2()], rate)
+            }
+        }
+    }
+}
\ No newline at end of file
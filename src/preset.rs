@@ -0,0 +1,131 @@
+use crate::filter_kind::FilterKind;
+use crate::parameters::SpecfilterParamData;
+use crate::response_type::ResponseType;
+
+pub const FACTORY_PRESET_COUNT: usize = 7;
+pub const FACTORY_PRESETS: [(&'static str, Option<SpecfilterParamData>); FACTORY_PRESET_COUNT] = [
+    ("Init", None),
+    ("Telephone band", Some(SpecfilterParamData {
+        filter_kind: FilterKind::Chebyshev1,
+        passband_ripple: 1.0,
+        stopband_attenuation: 40.0,
+        frequencies: [300.0, 3400.0],
+        bandwidths: [0.05, 0.18],
+        response_type: ResponseType::Free,
+        edges: None
+    })),
+    ("AM radio", Some(SpecfilterParamData {
+        filter_kind: FilterKind::Butterworth,
+        passband_ripple: 3.0,
+        stopband_attenuation: 30.0,
+        frequencies: [100.0, 5000.0],
+        bandwidths: [0.06, 0.3],
+        response_type: ResponseType::Free,
+        edges: None
+    })),
+    ("50 Hz hum notch", Some(SpecfilterParamData {
+        filter_kind: FilterKind::Elliptic,
+        passband_ripple: 1.0,
+        stopband_attenuation: 60.0,
+        frequencies: [55.0, 45.0],
+        bandwidths: [0.37, 0.42],
+        response_type: ResponseType::Free,
+        edges: None
+    })),
+    ("60 Hz hum notch", Some(SpecfilterParamData {
+        filter_kind: FilterKind::Elliptic,
+        passband_ripple: 1.0,
+        stopband_attenuation: 60.0,
+        frequencies: [66.0, 54.0],
+        bandwidths: [0.37, 0.42],
+        response_type: ResponseType::Free,
+        edges: None
+    })),
+    ("Sub-bass cut", Some(SpecfilterParamData {
+        filter_kind: FilterKind::Butterworth,
+        passband_ripple: 3.0,
+        stopband_attenuation: 40.0,
+        frequencies: [20.0, 30000.0],
+        bandwidths: [-0.048, 0.5],
+        response_type: ResponseType::Free,
+        edges: None
+    })),
+    ("Brickwall anti-alias", Some(SpecfilterParamData {
+        filter_kind: FilterKind::Elliptic,
+        passband_ripple: 1.0,
+        stopband_attenuation: 90.0,
+        frequencies: [1.0, 20000.0],
+        bandwidths: [0.5, -0.001],
+        response_type: ResponseType::Free,
+        edges: None
+    }))
+];
+
+/// A preset slot. Frequencies that depend on the sample rate for their normalized value are stored in Hz, and everything else normalized.
+#[derive(Clone, PartialEq)]
+pub struct SpecfilterPreset
+{
+    pub name: String,
+    pub values: Vec<f32>
+}
+
+impl SpecfilterPreset
+{
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let name = self.name.as_bytes();
+        [
+            (name.len() as u32).to_le_bytes().as_slice(),
+            name,
+            (self.values.len() as u32).to_le_bytes().as_slice(),
+            self.values.iter()
+                .map(|v| v.to_le_bytes())
+                .collect::<Vec<_>>()
+                .concat()
+                .as_slice()
+        ].concat()
+    }
+
+    /// Reads a preset from the front of `data`, advancing it past the bytes that were consumed.
+    pub fn from_bytes(data: &mut &[u8]) -> Option<Self>
+    {
+        let name_len = read_u32(data)? as usize;
+        if data.len() < name_len
+        {
+            return None
+        }
+        let (name, rest) = data.split_at(name_len);
+        *data = rest;
+        let name = String::from_utf8_lossy(name).into_owned();
+
+        let value_count = read_u32(data)? as usize;
+        if data.len() < value_count*4
+        {
+            return None
+        }
+        let (values, rest) = data.split_at(value_count*4);
+        *data = rest;
+        let values = values.array_chunks()
+            .map(|&b| f32::from_le_bytes(b))
+            .collect();
+
+        Some(Self {
+            name,
+            values
+        })
+    }
+}
+
+pub fn read_u32(data: &mut &[u8]) -> Option<u32>
+{
+    let (&b, rest) = data.split_first_chunk()?;
+    *data = rest;
+    Some(u32::from_le_bytes(b))
+}
+
+pub fn read_f32(data: &mut &[u8]) -> Option<f32>
+{
+    let (&b, rest) = data.split_first_chunk()?;
+    *data = rest;
+    Some(f32::from_le_bytes(b))
+}
\ No newline at end of file
@@ -0,0 +1,39 @@
+use core::fmt::Display;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum ResponseType
+{
+    Free,
+    LowPass,
+    HighPass,
+    BandPass,
+    BandStop
+}
+
+impl ResponseType
+{
+    pub const VARIANT_COUNT: usize = core::mem::variant_count::<Self>();
+    pub const VARIANTS: [Self; Self::VARIANT_COUNT] = [
+        Self::Free,
+        Self::LowPass,
+        Self::HighPass,
+        Self::BandPass,
+        Self::BandStop
+    ];
+    pub const VARIANT_NAMES: [&'static str; Self::VARIANT_COUNT] = [
+        "Free",
+        "Low-pass",
+        "High-pass",
+        "Band-pass",
+        "Band-stop"
+    ];
+}
+
+impl Display for ResponseType
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", Self::VARIANT_NAMES[*self as usize])
+    }
+}
\ No newline at end of file
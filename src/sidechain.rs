@@ -0,0 +1,77 @@
+use std::f64::consts::TAU;
+use std::ops::Range;
+
+use real_time_fir_iir_filters::{iir::second::SecondOrderFilter, Filter};
+
+use crate::envelope::EnvelopeFollower;
+use crate::parameters::SpecfilterParameters;
+use crate::sidechain_mode::SidechainMode;
+
+pub const SIDECHAIN_CHANNEL_COUNT: usize = 2;
+
+/// Follows the level of the sidechain input, optionally limited to a band around the sidechain frequency.
+/// Attack, release and detection are shared with the envelope follower of the main input.
+pub struct SidechainDetector
+{
+    band: [SecondOrderFilter<f64>; SIDECHAIN_CHANNEL_COUNT],
+    envelope: EnvelopeFollower
+}
+
+impl SidechainDetector
+{
+    pub fn new() -> Self
+    {
+        Self {
+            band: core::array::from_fn(|_| SecondOrderFilter::new(TAU*100.0, 0.5)),
+            envelope: EnvelopeFollower::new()
+        }
+    }
+
+    pub fn reset(&mut self)
+    {
+        for band in self.band.iter_mut()
+        {
+            band.reset()
+        }
+        self.envelope.reset()
+    }
+
+    /// Returns the sidechain level at the end of the block.
+    /// A disconnected sidechain, where the host gives no sidechain channels, reads as silence.
+    pub fn process(&mut self, param: &SpecfilterParameters, sidechain: &[Vec<f64>], block: Range<usize>, rate: f64) -> f32
+    {
+        let mode = param.sidechain_mode();
+        if mode == SidechainMode::Off || sidechain.is_empty()
+        {
+            self.reset();
+            return 0.0
+        }
+
+        let detector = param.envelope_detector();
+        let attack = param.envelope_attack.get()/1000.0;
+        let release = param.envelope_release.get()/1000.0;
+
+        match mode
+        {
+            SidechainMode::Off => 0.0,
+            SidechainMode::Envelope => self.envelope.process(sidechain, block, detector, attack, release, rate),
+            SidechainMode::BandEnvelope => {
+                let omega = TAU*(param.sidechain_frequency.get() as f64).min(rate*0.49);
+                let zeta = 0.5/param.sidechain_q.get() as f64;
+
+                let band: Vec<Vec<f64>> = sidechain.iter()
+                    .zip(self.band.iter_mut())
+                    .map(|(x, band)| {
+                        band.omega = omega;
+                        band.zeta = zeta;
+                        // The band-pass output peaks at Q, so it is scaled back to unity gain
+                        x[block.clone()].iter()
+                            .map(|&x| band.filter(rate, x)[1]*2.0*zeta)
+                            .collect()
+                    }).collect();
+
+                self.envelope.process(&band, 0..block.len(), detector, attack, release, rate)
+            }
+        }
+    }
+}
\ No newline at end of file
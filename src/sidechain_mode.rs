@@ -0,0 +1,33 @@
+use core::fmt::Display;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SidechainMode
+{
+    Off,
+    Envelope,
+    BandEnvelope
+}
+
+impl SidechainMode
+{
+    pub const VARIANT_COUNT: usize = core::mem::variant_count::<Self>();
+    pub const VARIANTS: [Self; Self::VARIANT_COUNT] = [
+        Self::Off,
+        Self::Envelope,
+        Self::BandEnvelope
+    ];
+    pub const VARIANT_NAMES: [&'static str; Self::VARIANT_COUNT] = [
+        "Off",
+        "Envelope",
+        "Band envelope"
+    ];
+}
+
+impl Display for SidechainMode
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", Self::VARIANT_NAMES[*self as usize])
+    }
+}
\ No newline at end of file
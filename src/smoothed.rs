@@ -0,0 +1,37 @@
+/// Largest difference at which a glide counts as finished.
+const SETTLE: f64 = 1e-6;
+
+/// A value that glides exponentially towards its target, by a fraction of the way every sample.
+/// It starts out on the first target it is given, so that nothing fades in when processing starts.
+#[derive(Clone, Copy)]
+pub struct Smoothed
+{
+    value: Option<f64>
+}
+
+impl Smoothed
+{
+    pub fn new() -> Self
+    {
+        Self {
+            value: None
+        }
+    }
+
+    pub fn reset(&mut self)
+    {
+        self.value = None
+    }
+
+    /// Moves the value the fraction `change` of the way towards `target`, and returns it.
+    pub fn next(&mut self, target: f64, change: f64) -> f64
+    {
+        let value = match self.value
+        {
+            Some(value) if (target - value).abs() > SETTLE => value + (target - value)*change,
+            _ => target
+        };
+        self.value = Some(value);
+        value
+    }
+}
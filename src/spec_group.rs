@@ -0,0 +1,45 @@
+use core::fmt::Display;
+
+use crate::parameters::SpecfilterParam;
+
+/// One of the two sets of spec parameters.
+/// In mid/side mode, A filters the mid channel and B the side channel, and in dual mono mode A filters the left channel and B the right channel.
+/// The spec mode and its center, width and edge parameters only apply to A, while B is always given by its frequencies and bandwidths.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum SpecGroup
+{
+    A,
+    B
+}
+
+impl SpecGroup
+{
+    pub const VARIANT_COUNT: usize = core::mem::variant_count::<Self>();
+    pub const VARIANTS: [Self; Self::VARIANT_COUNT] = [
+        Self::A,
+        Self::B
+    ];
+    pub const VARIANT_NAMES: [&'static str; Self::VARIANT_COUNT] = [
+        "A",
+        "B"
+    ];
+
+    /// The frequency and bandwidth parameters of the group, which are reset when its filter can not be designed.
+    pub fn band_params(&self) -> [SpecfilterParam; 4]
+    {
+        match self
+        {
+            Self::A => [SpecfilterParam::Frequency1, SpecfilterParam::Frequency2, SpecfilterParam::Bandwidth1, SpecfilterParam::Bandwidth2],
+            Self::B => [SpecfilterParam::Frequency1B, SpecfilterParam::Frequency2B, SpecfilterParam::Bandwidth1B, SpecfilterParam::Bandwidth2B]
+        }
+    }
+}
+
+impl Display for SpecGroup
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", Self::VARIANT_NAMES[*self as usize])
+    }
+}
\ No newline at end of file
@@ -0,0 +1,62 @@
+use core::fmt::Display;
+
+use crate::tube_stage::TubeCharacter;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TubePreset
+{
+    Custom,
+    Clean,
+    Warm,
+    Hot
+}
+
+impl TubePreset
+{
+    pub const VARIANT_COUNT: usize = core::mem::variant_count::<Self>();
+    pub const VARIANTS: [Self; Self::VARIANT_COUNT] = [
+        Self::Custom,
+        Self::Clean,
+        Self::Warm,
+        Self::Hot
+    ];
+    pub const VARIANT_NAMES: [&'static str; Self::VARIANT_COUNT] = [
+        "Custom",
+        "Clean",
+        "Warm",
+        "Hot"
+    ];
+
+    /// The character the preset sets the tube stage to. Warm is the original voicing.
+    pub fn character(&self) -> Option<TubeCharacter>
+    {
+        match self
+        {
+            Self::Custom => None,
+            Self::Clean => Some(TubeCharacter {
+                drive: -12.0,
+                bias: 0.0,
+                overtones: 0.5,
+                softness: 1.5,
+                ..Default::default()
+            }),
+            Self::Warm => Some(TubeCharacter::default()),
+            Self::Hot => Some(TubeCharacter {
+                drive: 12.0,
+                bias: 0.2,
+                overtones: 1.5,
+                softness: 0.5,
+                corners: [47.0, 4700.0]
+            })
+        }
+    }
+}
+
+impl Display for TubePreset
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", Self::VARIANT_NAMES[*self as usize])
+    }
+}
\ No newline at end of file
@@ -21,11 +21,84 @@ const G_OVERTONES: f64 = 1.0;
 const P_SOFT_CLIP: f64 = 1.0;
 const G_SOFT_CLIP: f64 = 1.13;
 const P_SIGMOID_CLIP: f64 = 0.99;
+/// Largest difference, in the units of each part of the character, at which a glide counts as finished.
+const CHARACTER_SETTLE: f64 = 1e-4;
+
+/// The character of the tube stage. The defaults give its original voicing.
+#[derive(Clone, Copy, PartialEq)]
+pub struct TubeCharacter
+{
+    /// Gain into the clipping stages in dB, which is taken back out after them.
+    pub drive: f64,
+    /// Offset into the sigmoids, which makes the clipping asymmetric and brings in even harmonics.
+    pub bias: f64,
+    pub overtones: f64,
+    pub softness: f64,
+    /// Corner frequencies of the first and last filter of the cascade, in Hz.
+    /// The filters in between keep their places on the curve between them.
+    pub corners: [f64; 2]
+}
+
+impl Default for TubeCharacter
+{
+    fn default() -> Self
+    {
+        Self {
+            drive: 0.0,
+            bias: 0.0,
+            overtones: G_OVERTONES,
+            softness: P_SOFT_CLIP,
+            corners: [FILTER_TUBE_FREQUENCIES[0], FILTER_TUBE_FREQUENCIES[FILTER_TUBE_CASCADE - 1]]
+        }
+    }
+}
+
+impl TubeCharacter
+{
+    /// Moves towards `new` by the fraction `change`, with the corner frequencies moving in octaves.
+    fn change(&mut self, new: TubeCharacter, change: f64)
+    {
+        self.drive = new.drive*change + self.drive*(1.0 - change);
+        self.bias = new.bias*change + self.bias*(1.0 - change);
+        self.overtones = new.overtones*change + self.overtones*(1.0 - change);
+        self.softness = new.softness*change + self.softness*(1.0 - change);
+        for (f1, f2) in self.corners.iter_mut()
+            .zip(new.corners)
+        {
+            *f1 = (f2.log2()*change + f1.log2()*(1.0 - change)).exp2()
+        }
+
+        // Snaps to the target once the remaining difference is inaudible, so the filters stop being retuned
+        let distance = [self.drive - new.drive, self.bias - new.bias, self.overtones - new.overtones, self.softness - new.softness]
+            .into_iter()
+            .chain(self.corners.iter()
+                .zip(new.corners)
+                .map(|(f1, f2)| (f1/f2).log2())
+            ).fold(0.0, |d, x| x.abs().max(d));
+        if !(distance > CHARACTER_SETTLE)
+        {
+            *self = new
+        }
+    }
+
+    fn frequencies(&self) -> [f64; FILTER_TUBE_CASCADE]
+    {
+        let [low, high] = Self::default().corners;
+        let scale = [self.corners[0]/low, self.corners[1]/high];
+        core::array::from_fn(|k| {
+            let t = k as f64/(FILTER_TUBE_CASCADE - 1) as f64;
+            FILTER_TUBE_FREQUENCIES[k]*scale[0].powf(1.0 - t)*scale[1].powf(t)
+        })
+    }
+}
 
 #[derive(Clone, Copy)]
 pub struct TubeStage
 {
     filter_tube: [FirstOrderFilter<f64>; FILTER_TUBE_CASCADE],
+    character: TubeCharacter,
+    target: TubeCharacter,
+    change: f64
 }
 
 impl TubeStage
@@ -35,17 +108,45 @@ impl TubeStage
         let x = x.max(min).min(max);
         x - 1.0/(max - x).exp() + 1.0/(x - min).exp()
     }
-    
+
+    /// The sigmoid clipper, shifted by the bias so that silence still maps to silence.
+    fn sigmoid_clip(x: f64, bias: f64) -> f64
+    {
+        let sigmoid = |x: f64| P_SIGMOID_CLIP*((x*2.0).exp() - 1.0)/(1.0 + (x*2.0).exp()) + (1.0 - P_SIGMOID_CLIP)*x;
+        sigmoid(x + bias) - sigmoid(bias)
+    }
 
     pub fn new() -> Self
     {
         Self {
-            filter_tube: FILTER_TUBE_FREQUENCIES.map(|f| FirstOrderFilter::new(TAU*f))
+            filter_tube: FILTER_TUBE_FREQUENCIES.map(|f| FirstOrderFilter::new(TAU*f)),
+            character: TubeCharacter::default(),
+            target: TubeCharacter::default(),
+            change: 1.0
         }
     }
 
+    /// Sets the character to glide towards, by the fraction `change` every sample.
+    pub fn set_character(&mut self, target: TubeCharacter, change: f64)
+    {
+        self.target = target;
+        self.change = change;
+    }
+
     pub fn next(&mut self, rate: f64, mut x: f64) -> f64
     {
+        if self.character != self.target
+        {
+            self.character.change(self.target, self.change);
+            for (filter_tube, f) in self.filter_tube.iter_mut()
+                .zip(self.character.frequencies())
+            {
+                filter_tube.omega = TAU*f
+            }
+        }
+        let TubeCharacter {drive, bias, overtones, softness, ..} = self.character;
+        let g_clip = G_CLIP*10f64.powf(drive/20.0);
+
         x = Self::soft_clip(x, -200.0, 140.0);
         x = Self::soft_clip(x, -20.0, 14.0)*P_HARD_CLIP_FINAL + (1.0 - P_HARD_CLIP_FINAL)*x;
 
@@ -54,23 +155,30 @@ impl TubeStage
         {
             let [z1, z2] = filter_tube.filter(rate, x);
 
-            x = z2*G_OVERTONES - z1*P_SOFT_CLIP;
-            x = G_CLIP*x;
-            x = P_SIGMOID_CLIP*((x*2.0).exp() - 1.0)/(1.0 + (x*2.0).exp()) + (1.0 - P_SIGMOID_CLIP)*x;
-            x = x/G_CLIP;
+            x = z2*overtones - z1*softness;
+            x = g_clip*x;
+            x = Self::sigmoid_clip(x, bias);
+            x = x/g_clip;
             let div = 1.0/(k as f64 + 2.0).sqrt();
             x = Self::soft_clip(x, -200.0*div, 140.0*div)*P_HARD_CLIP + (1.0 - P_HARD_CLIP)*x;
 
-            x = z1*(1.0 + P_SOFT_CLIP*G_SOFT_CLIP) + x*G_SOFT_CLIP;
+            x = z1*(1.0 + softness*G_SOFT_CLIP) + x*G_SOFT_CLIP;
         }
         
-        x = G_CLIP*x;
-        x = P_SIGMOID_CLIP*((x*2.0).exp() - 1.0)/(1.0 + (x*2.0).exp()) + (1.0 - P_SIGMOID_CLIP)*x;
-        x = x/G_CLIP;
+        x = g_clip*x;
+        x = Self::sigmoid_clip(x, bias);
+        x = x/g_clip;
 
         x
     }
 
+    /// Samples until the slowest of the tube filters has decayed by the given amount of decibels.
+    pub fn tail(&self, db: f64, rate: f64) -> f64
+    {
+        let tau = rate/(TAU*self.character.corners[0].min(self.target.corners[0]));
+        tau*db/20.0*core::f64::consts::LN_10
+    }
+
     pub fn reset(&mut self)
     {
         for filter in self.filter_tube.iter_mut()
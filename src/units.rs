@@ -0,0 +1,312 @@
+use core::f32::consts::LN_2;
+
+use crate::bandwidth_unit::BandwidthUnit;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Width
+{
+    Hertz(f32),
+    Octaves(f32),
+    Percent(f32),
+    Q(f32),
+    Erb(f32)
+}
+
+impl Width
+{
+    /// Width in octaves of a band with the given quality factor.
+    pub fn q_to_octaves(q: f32) -> f32
+    {
+        2.0/LN_2*(0.5/q).asinh()
+    }
+
+    /// Quality factor of a band with the given width in octaves.
+    pub fn octaves_to_q(octaves: f32) -> f32
+    {
+        0.5/(0.5*LN_2*octaves).sinh()
+    }
+
+    /// Measures the band from `f0` to `f1` in the given unit.
+    /// Percent is the width relative to the lower edge.
+    pub fn of_band(unit: BandwidthUnit, f0: f32, f1: f32) -> Self
+    {
+        match unit
+        {
+            BandwidthUnit::Hertz => Self::Hertz(f1 - f0),
+            BandwidthUnit::Octaves => Self::Octaves((f1/f0).log2()),
+            BandwidthUnit::Percent => Self::Percent(100.0*(f1/f0 - 1.0)),
+            BandwidthUnit::Q => Self::Q(Self::octaves_to_q((f1/f0).log2())),
+            BandwidthUnit::Erb => Self::Erb(erb_number(f1) - erb_number(f0))
+        }
+    }
+
+    pub fn unit(&self) -> BandwidthUnit
+    {
+        match self
+        {
+            Self::Hertz(_) => BandwidthUnit::Hertz,
+            Self::Octaves(_) => BandwidthUnit::Octaves,
+            Self::Percent(_) => BandwidthUnit::Percent,
+            Self::Q(_) => BandwidthUnit::Q,
+            Self::Erb(_) => BandwidthUnit::Erb
+        }
+    }
+
+    pub fn value(&self) -> f32
+    {
+        match *self
+        {
+            Self::Hertz(w) | Self::Octaves(w) | Self::Percent(w) | Self::Q(w) | Self::Erb(w) => w
+        }
+    }
+
+    pub fn new(unit: BandwidthUnit, value: f32) -> Self
+    {
+        match unit
+        {
+            BandwidthUnit::Hertz => Self::Hertz(value),
+            BandwidthUnit::Octaves => Self::Octaves(value),
+            BandwidthUnit::Percent => Self::Percent(value),
+            BandwidthUnit::Q => Self::Q(value),
+            BandwidthUnit::Erb => Self::Erb(value)
+        }
+    }
+
+    pub fn label(&self) -> &'static str
+    {
+        match *self
+        {
+            Self::Hertz(_) => "",
+            Self::Octaves(_) => "oct",
+            Self::Percent(_) => "%",
+            Self::Q(_) => "Q",
+            Self::Erb(_) => "ERB"
+        }
+    }
+
+    pub fn format(&self) -> String
+    {
+        match *self
+        {
+            Self::Hertz(w) => format_frequency(w),
+            w => format!("{:.3}", w.value())
+        }
+    }
+}
+
+/// Position of a frequency on the ERB-number scale of Glasberg and Moore.
+pub fn erb_number(f: f32) -> f32
+{
+    21.4*(1.0 + 0.00437*f).log10()
+}
+
+/// Lowercases and removes all whitespace, so that "440 Hz" and "440hz" parse the same.
+fn normalize(text: &str) -> String
+{
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// Splits a leading decimal number from its unit suffix.
+fn split_unit(text: &str) -> Option<(f32, &str)>
+{
+    let end = text.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(text.len());
+    let value = text[..end].parse::<f32>()
+        .ok()?;
+    Some((value, &text[end..]))
+}
+
+pub fn note_frequency(note: f32) -> f32
+{
+    440.0*((note - 69.0)/12.0).exp2()
+}
+
+/// Parses a note name in scientific pitch notation, like "A4", "C#3" or "Bb-1".
+pub fn parse_note(text: &str) -> Option<f32>
+{
+    let text = normalize(text);
+    let mut chars = text.chars();
+    let semitone = match chars.next()?
+    {
+        'c' => 0,
+        'd' => 2,
+        'e' => 4,
+        'f' => 5,
+        'g' => 7,
+        'a' => 9,
+        'b' => 11,
+        _ => return None
+    };
+    let rest = chars.as_str();
+    let (accidental, rest) = if let Some(rest) = rest.strip_prefix('#')
+    {
+        (1, rest)
+    }
+    else if let Some(rest) = rest.strip_prefix('b')
+    {
+        (-1, rest)
+    }
+    else
+    {
+        (0, rest)
+    };
+    let octave = rest.parse::<i32>()
+        .ok()?;
+    Some(note_frequency(((octave + 1)*12 + semitone + accidental) as f32))
+}
+
+pub fn parse_frequency(text: &str) -> Option<f32>
+{
+    if let Some(f) = parse_note(text)
+    {
+        return Some(f)
+    }
+    let text = normalize(text);
+    let (value, unit) = split_unit(&text)?;
+    match unit
+    {
+        "" | "hz" => Some(value),
+        "k" | "khz" => Some(value*1000.0),
+        _ => None
+    }
+}
+
+pub fn parse_decibels(text: &str) -> Option<f32>
+{
+    let text = normalize(text);
+    let (value, unit) = split_unit(&text)?;
+    match unit
+    {
+        "" | "db" => Some(value),
+        _ => None
+    }
+}
+
+pub fn parse_percent(text: &str) -> Option<f32>
+{
+    let text = normalize(text);
+    let (value, unit) = split_unit(&text)?;
+    match unit
+    {
+        "" | "%" => Some(value),
+        _ => None
+    }
+}
+
+pub fn parse_semitones(text: &str) -> Option<f32>
+{
+    let text = normalize(text);
+    let (value, unit) = split_unit(&text)?;
+    match unit
+    {
+        "" | "st" | "semi" | "semitone" | "semitones" => Some(value),
+        _ => None
+    }
+}
+
+pub fn parse_milliseconds(text: &str) -> Option<f32>
+{
+    let text = normalize(text);
+    let (value, unit) = split_unit(&text)?;
+    match unit
+    {
+        "" | "ms" => Some(value),
+        "s" => Some(value*1000.0),
+        _ => None
+    }
+}
+
+pub fn parse_octaves(text: &str) -> Option<f32>
+{
+    let text = normalize(text);
+    let (value, unit) = split_unit(&text)?;
+    match unit
+    {
+        "" | "oct" | "octave" | "octaves" => Some(value),
+        _ => None
+    }
+}
+
+pub fn parse_degrees(text: &str) -> Option<f32>
+{
+    let text = normalize(text);
+    let (value, unit) = split_unit(&text)?;
+    match unit
+    {
+        "" | "°" | "deg" | "degrees" => Some(value),
+        _ => None
+    }
+}
+
+pub fn parse_switch(text: &str) -> Option<bool>
+{
+    match normalize(text).as_str()
+    {
+        "on" | "1" => Some(true),
+        "off" | "0" => Some(false),
+        _ => None
+    }
+}
+
+/// Parses a width, where a bare number is taken to be in the given unit.
+pub fn parse_width(text: &str, unit: BandwidthUnit) -> Option<Width>
+{
+    let text = normalize(text);
+    if let Some(q) = text.strip_prefix('q')
+    {
+        return q.parse::<f32>()
+            .ok()
+            .map(Width::Q)
+    }
+    let (value, suffix) = split_unit(&text)?;
+    match suffix
+    {
+        "" => Some(Width::new(unit, value)),
+        "hz" => Some(Width::Hertz(value)),
+        "k" | "khz" => Some(Width::Hertz(value*1000.0)),
+        "oct" | "octave" | "octaves" => Some(Width::Octaves(value)),
+        "%" => Some(Width::Percent(value)),
+        "q" => Some(Width::Q(value)),
+        "erb" | "erbs" => Some(Width::Erb(value)),
+        _ => None
+    }
+}
+
+/// Formats a frequency with its unit in the text, so that typing it back in gives the same frequency.
+pub fn format_frequency(f: f32) -> String
+{
+    if f.abs() >= 1000.0
+    {
+        format!("{:.2} kHz", f/1000.0)
+    }
+    else
+    {
+        format!("{:.1} Hz", f)
+    }
+}
+
+pub fn db_to_gain(db: f32) -> f32
+{
+    10.0f32.powf(db/20.0)
+}
+
+#[test]
+fn test_parse()
+{
+    assert_eq!(parse_frequency("1.2k"), Some(1200.0));
+    assert_eq!(parse_frequency("440 Hz"), Some(440.0));
+    assert_eq!(parse_frequency("A4"), Some(440.0));
+    assert_eq!(parse_frequency("a3"), Some(220.0));
+    assert_eq!(parse_decibels("-3 dB"), Some(-3.0));
+    assert_eq!(parse_width("2 oct", BandwidthUnit::Hertz), Some(Width::Octaves(2.0)));
+    assert_eq!(parse_width("Q 4.3", BandwidthUnit::Hertz), Some(Width::Q(4.3)));
+    assert_eq!(parse_width("1.5", BandwidthUnit::Erb), Some(Width::Erb(1.5)));
+    assert_eq!(parse_percent("50 %"), Some(50.0));
+    assert_eq!(parse_frequency("loud"), None);
+    assert_eq!(parse_frequency(&format_frequency(1200.0)), Some(1200.0));
+    assert_eq!(parse_frequency(&format_frequency(440.0)), Some(440.0));
+    assert_eq!(parse_width(&Width::Hertz(2500.0).format(), BandwidthUnit::Octaves), Some(Width::Hertz(2500.0)));
+}
\ No newline at end of file